@@ -1,3 +1,5 @@
+pub mod editor;
+pub mod filter;
 pub mod lexer;
 pub mod path;
 pub mod query;
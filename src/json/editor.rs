@@ -0,0 +1,1041 @@
+use std::collections::VecDeque;
+
+use super::{
+    lexer::{JsonStreamLexer, JsonStreamStatus, JsonToken},
+    path::JsonPath,
+    query::{JsonQuery, JsonQueryMode, JsonQueryStatus},
+};
+
+pub enum JsonEdit {
+    Set(Vec<JsonToken>),
+    Delete,
+    Insert { name: String, value: Vec<JsonToken> },
+    Append(Vec<JsonToken>),
+    Prepend(Vec<JsonToken>),
+    Rename(String),
+}
+
+impl JsonEdit {
+    pub fn set(value: &str) -> Result<JsonEdit, String> {
+        Ok(JsonEdit::Set(tokenize_value(value)?))
+    }
+
+    pub fn insert(name: &str, value: &str) -> Result<JsonEdit, String> {
+        Ok(JsonEdit::Insert {
+            name: String::from(name),
+            value: tokenize_value(value)?,
+        })
+    }
+
+    pub fn append(value: &str) -> Result<JsonEdit, String> {
+        Ok(JsonEdit::Append(tokenize_value(value)?))
+    }
+
+    pub fn prepend(value: &str) -> Result<JsonEdit, String> {
+        Ok(JsonEdit::Prepend(tokenize_value(value)?))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum JsonEditPhase {
+    Leading,
+    AfterValue,
+    Removed,
+    /// The first entry was removed, so the entry after it takes its leading whitespace in place of its own.
+    SkipLeading,
+}
+
+/// The whitespace and new lines around an existing entry, used to lay out new entries the same way as their siblings.
+#[derive(Clone, Default)]
+struct JsonEntryLayout {
+    leading: Vec<JsonToken>,
+    name_trivia: Vec<JsonToken>,
+    value_trivia: Vec<JsonToken>,
+}
+
+/// An object or array that is open in the document being edited.
+///
+/// Everything between the start of an entry (the `{`, `[` or delimiter before it) and the first token of its value is held
+/// back in `pending` until it is known whether the entry matches. This is what allows a removed entry to take its
+/// delimiter, whitespace and new lines with it.
+struct JsonEditFrame {
+    phase: JsonEditPhase,
+    is_target: bool,
+    is_done: bool,
+    entries: usize,
+    layout: Option<JsonEntryLayout>,
+    removed_leading: Option<Vec<JsonToken>>,
+    is_renamed: bool,
+    new_names: usize,
+}
+
+impl JsonEditFrame {
+    fn new(is_target: bool) -> JsonEditFrame {
+        JsonEditFrame {
+            phase: JsonEditPhase::Leading,
+            is_target,
+            is_done: false,
+            entries: 0,
+            layout: None,
+            removed_leading: None,
+            is_renamed: false,
+            new_names: 0,
+        }
+    }
+}
+
+pub struct JsonStreamEditor<'a> {
+    query: JsonQuery<'a>,
+    edit: JsonEdit,
+    tokens: VecDeque<JsonToken>,
+    pending: Vec<JsonToken>,
+    frames: Vec<JsonEditFrame>,
+    skip_depth: usize,
+    indentation: Option<String>,
+    value_trivia: Option<Vec<JsonToken>>,
+    delimiter_trivia: Option<Vec<JsonToken>>,
+    is_new_line: bool,
+    error: Option<String>,
+}
+
+impl<'a> JsonStreamEditor<'a> {
+    pub fn from(path: &'a JsonPath, edit: JsonEdit) -> JsonStreamEditor<'a> {
+        // Renaming changes the name of the member rather than its value so the query needs to capture the name instead.
+        let mode = match edit {
+            JsonEdit::Rename(_) => JsonQueryMode::Key,
+            _ => JsonQueryMode::Value,
+        };
+
+        JsonStreamEditor {
+            query: JsonQuery::with_mode(path, mode),
+            edit,
+            tokens: VecDeque::new(),
+            pending: Vec::new(),
+            frames: vec![JsonEditFrame::new(false)],
+            skip_depth: 0,
+            indentation: None,
+            value_trivia: None,
+            delimiter_trivia: None,
+            is_new_line: false,
+            error: None,
+        }
+    }
+
+    /// Finishes off the edit. An error is returned if the edit couldn't be made.
+    pub fn close(&mut self) -> Result<(), String> {
+        self.query.close();
+        self.pop_query_tokens();
+        self.drop_held_delimiter();
+        self.flush_pending();
+
+        match &self.error {
+            Some(error) => Err(error.clone()),
+            None => Ok(()),
+        }
+    }
+
+    pub fn pop_token(&mut self) -> JsonStreamStatus {
+        match self.tokens.pop_front() {
+            Some(token) => JsonStreamStatus::Token(token),
+            None => JsonStreamStatus::None,
+        }
+    }
+
+    pub fn push_token(&mut self, token: JsonToken) -> Result<(), String> {
+        self.query.push_token(token);
+        self.pop_query_tokens();
+
+        match &self.error {
+            Some(error) => Err(error.clone()),
+            None => Ok(()),
+        }
+    }
+
+    /// Edits the tokens the query has decided on, which lag behind those pushed while it waits to find out whether an
+    /// array item is selected.
+    fn pop_query_tokens(&mut self) {
+        while let JsonQueryStatus::Token { token, capture, .. } = self.query.pop_token() {
+            self.edit_token(token, capture);
+        }
+    }
+
+    fn edit_token(&mut self, token: JsonToken, capture: bool) {
+        // The first indentation in the document is used as the unit of indentation when an entry is added to an empty
+        // object or array that spans several lines.
+        if let JsonToken::Whitespace(whitespace) = &token {
+            if self.is_new_line && self.indentation.is_none() {
+                self.indentation = Some(whitespace.clone());
+            }
+        }
+
+        self.is_new_line = matches!(token, JsonToken::NewLine(_));
+
+        if self.skip_depth > 0 {
+            match token {
+                JsonToken::ObjectOpen(_) | JsonToken::ArrayOpen(_) => self.skip_depth += 1,
+                JsonToken::ObjectClose(_) | JsonToken::ArrayClose(_) => self.skip_depth -= 1,
+                _ => {}
+            }
+
+            return;
+        }
+
+        // A member can't be renamed to a name that another member of the same object has or is being renamed to, as the
+        // object would end up with two members of the same name.
+        if let (JsonToken::PropertyName { raw: _, name }, JsonEdit::Rename(new_name), Some(frame)) = (&token, &self.edit, self.frames.last_mut()) {
+            frame.is_renamed |= capture;
+
+            if capture || name == new_name {
+                frame.new_names += 1;
+            }
+
+            if frame.is_renamed && frame.new_names > 1 {
+                let error = format!("a member can't be renamed to '{}' as the object already has one with that name", new_name);
+                self.set_error(error.as_str());
+            }
+        }
+
+        match token {
+            JsonToken::Whitespace(_) | JsonToken::NewLine(_) => match self.phase() {
+                JsonEditPhase::SkipLeading => {}
+                JsonEditPhase::Leading | JsonEditPhase::Removed => self.pending.push(token),
+                JsonEditPhase::AfterValue if self.is_target() => self.pending.push(token),
+                JsonEditPhase::AfterValue => self.tokens.push_back(token),
+            },
+            JsonToken::PropertyName { raw, name: _ } if capture && matches!(self.edit, JsonEdit::Rename(_)) => {
+                if let JsonEdit::Rename(name) = &self.edit {
+                    self.pending.push(JsonToken::PropertyName {
+                        raw: requote(name, &raw),
+                        name: name.clone(),
+                    });
+                }
+            }
+            JsonToken::PropertyName { raw: _, name: _ } | JsonToken::KeyValueDelimiter(_) => {
+                self.pending.push(token);
+
+                if self.phase() == JsonEditPhase::SkipLeading {
+                    self.set_phase(JsonEditPhase::Leading);
+                }
+            }
+            JsonToken::ArrayItemDelimiter(_) | JsonToken::PropertyDelimiter(_) => {
+                if self.phase() == JsonEditPhase::Removed {
+                    // The delimiter after a removed entry goes with it, along with any whitespace before it. If it was the
+                    // first entry there's no delimiter before it, so the next entry moves up into its place instead.
+                    match self.frames.last_mut().and_then(|frame| frame.removed_leading.take()) {
+                        Some(leading) => {
+                            self.pending = leading;
+                            self.set_phase(JsonEditPhase::SkipLeading);
+                        }
+                        None => {
+                            self.pending.retain(is_delimiter);
+                            self.set_phase(JsonEditPhase::Leading);
+                        }
+                    }
+                } else {
+                    self.flush_pending();
+                    self.pending.push(token);
+                    self.set_phase(JsonEditPhase::Leading);
+                }
+            }
+            JsonToken::ObjectClose(_) | JsonToken::ArrayClose(_) => {
+                if self.phase() == JsonEditPhase::Removed {
+                    // The last entry was removed so the delimiter before it would now be a trailing delimiter.
+                    self.drop_held_delimiter();
+                }
+
+                if self.is_target() {
+                    self.push_new_entry();
+                }
+
+                self.flush_pending();
+                self.tokens.push_back(token);
+
+                if self.frames.len() > 1 {
+                    self.frames.pop();
+                }
+            }
+            _ => self.push_value(token, capture),
+        }
+    }
+
+    /// Handles the first token of a value, which is either a scalar or the opening of an object or array.
+    fn push_value(&mut self, token: JsonToken, capture: bool) {
+        let is_container = matches!(token, JsonToken::ObjectOpen(_) | JsonToken::ArrayOpen(_));
+        let is_match = capture && !self.frames.iter().any(|frame| frame.is_target);
+        let is_target = self.is_target();
+
+        self.record_layout(is_container);
+
+        if is_target && matches!(self.edit, JsonEdit::Prepend(_)) {
+            self.push_first_entry();
+        }
+
+        let (phase, frame) = match &self.edit {
+            JsonEdit::Set(value) if is_match => {
+                self.tokens.extend(self.pending.drain(..));
+                self.tokens.extend(value.iter().cloned());
+                (JsonEditPhase::AfterValue, None)
+            }
+            JsonEdit::Delete if is_match && self.frames.len() == 1 => {
+                self.set_error("the whole document can't be deleted");
+                return;
+            }
+            JsonEdit::Delete if is_match => {
+                let removed_leading = match self.pending.iter().any(is_delimiter) {
+                    true => None,
+                    false => Some(
+                        self.pending
+                            .iter()
+                            .take_while(|token| matches!(token, JsonToken::Whitespace(_) | JsonToken::NewLine(_)))
+                            .cloned()
+                            .collect(),
+                    ),
+                };
+
+                if let Some(frame) = self.frames.last_mut() {
+                    frame.removed_leading = removed_leading;
+                }
+
+                self.pending.retain(is_delimiter);
+                (JsonEditPhase::Removed, None)
+            }
+            JsonEdit::Insert { name: _, value: _ } if is_match && !matches!(token, JsonToken::ObjectOpen(_)) => {
+                self.set_error("a member can only be inserted into an object");
+                return;
+            }
+            JsonEdit::Append(_) | JsonEdit::Prepend(_) if is_match && !matches!(token, JsonToken::ArrayOpen(_)) => {
+                self.set_error("an item can only be added to an array");
+                return;
+            }
+            // Only member names are captured when renaming, so a value that's captured is an array item or the root.
+            JsonEdit::Rename(_) if is_match => {
+                self.set_error("only a member of an object can be renamed");
+                return;
+            }
+            // The member being inserted already exists so its value is replaced instead.
+            JsonEdit::Insert { name, value } if is_target && pending_name(&self.pending) == Some(name) => {
+                self.tokens.extend(self.pending.drain(..));
+                self.tokens.extend(value.iter().cloned());
+
+                if let Some(frame) = self.frames.last_mut() {
+                    frame.is_done = true;
+                }
+
+                (JsonEditPhase::AfterValue, None)
+            }
+            _ => {
+                self.flush_pending();
+                self.tokens.push_back(token.clone());
+                let is_target = match (&self.edit, &token) {
+                    (JsonEdit::Insert { name: _, value: _ }, JsonToken::ObjectOpen(_)) => is_match,
+                    (JsonEdit::Append(_) | JsonEdit::Prepend(_), JsonToken::ArrayOpen(_)) => is_match,
+                    _ => false,
+                };
+
+                (JsonEditPhase::AfterValue, Some(JsonEditFrame::new(is_target)))
+            }
+        };
+
+        self.set_phase(phase);
+
+        if is_container {
+            match frame {
+                Some(frame) => self.frames.push(frame),
+                None => self.skip_depth = 1,
+            }
+        }
+    }
+
+    /// Writes out the entry being added to the object or array that is about to close. The entry goes after the last
+    /// existing entry and before the whitespace and new lines leading up to the closing bracket.
+    fn push_new_entry(&mut self) {
+        let frame = match self.frames.last() {
+            Some(frame) if !frame.is_done => frame,
+            _ => return,
+        };
+
+        let closing: Vec<JsonToken> = self.pending.drain(..).collect();
+
+        let layout = match &frame.layout {
+            Some(layout) if frame.entries > 0 => {
+                self.tokens.push_back(self.delimiter());
+
+                let mut layout = layout.clone();
+
+                if frame.entries == 1 && layout.leading.is_empty() {
+                    layout.leading = self.separator();
+                }
+
+                layout
+            }
+            _ => JsonEntryLayout {
+                leading: self.empty_leading(&closing),
+                name_trivia: Vec::new(),
+                value_trivia: self.value_trivia.clone().unwrap_or_else(|| vec![JsonToken::Whitespace(String::from(" "))]),
+            },
+        };
+
+        match &self.edit {
+            JsonEdit::Insert { name, value } => {
+                self.tokens.extend(layout.leading);
+                self.tokens.push_back(JsonToken::PropertyName { raw: quote(name), name: name.clone() });
+                self.tokens.extend(layout.name_trivia);
+                self.tokens.push_back(JsonToken::KeyValueDelimiter(String::from(":")));
+                self.tokens.extend(layout.value_trivia);
+                self.tokens.extend(value.iter().cloned());
+            }
+            JsonEdit::Append(value) | JsonEdit::Prepend(value) => {
+                self.tokens.extend(layout.leading);
+                self.tokens.extend(value.iter().cloned());
+            }
+            JsonEdit::Set(_) | JsonEdit::Delete | JsonEdit::Rename(_) => {}
+        }
+
+        self.pending = closing;
+    }
+
+    /// Writes out the item being prepended ahead of the first item of the array, which is about to be written. The new item
+    /// takes the first item's place after the `[` and the first item moves along as though it had always been second.
+    fn push_first_entry(&mut self) {
+        let value = match (&self.edit, self.frames.last_mut()) {
+            (JsonEdit::Prepend(value), Some(frame)) if !frame.is_done => {
+                frame.is_done = true;
+                value
+            }
+            _ => return,
+        };
+
+        let leading: Vec<JsonToken> = self.pending.drain(..).collect();
+
+        self.tokens.extend(leading.iter().cloned());
+        self.tokens.extend(value.iter().cloned());
+        self.tokens.push_back(JsonToken::ArrayItemDelimiter(String::from(",")));
+
+        self.pending = match leading.is_empty() {
+            true => self.separator(),
+            false => leading,
+        };
+    }
+
+    /// Works out the whitespace to put after a new delimiter when nothing separates the opening bracket from the entry next
+    /// to it. The spacing used after delimiters elsewhere in the document is followed, or failing that the spacing after
+    /// `:`, e.g. `{"a": [1]}` becomes `{"a": [1, 2]}`.
+    fn separator(&self) -> Vec<JsonToken> {
+        match (&self.delimiter_trivia, &self.value_trivia) {
+            (Some(delimiter_trivia), _) => delimiter_trivia.clone(),
+            (None, Some(value_trivia)) => inline(value_trivia),
+            (None, None) => Vec::new(),
+        }
+    }
+
+    /// Works out the whitespace to put before the first entry of an empty object or array. If the closing bracket is on a
+    /// line of its own then the entry goes on a new line, indented one level deeper than the bracket.
+    fn empty_leading(&self, closing: &[JsonToken]) -> Vec<JsonToken> {
+        let new_line = closing.iter().rposition(|token| matches!(token, JsonToken::NewLine(_)));
+
+        match new_line {
+            Some(i) => {
+                let mut indentation = String::new();
+
+                for token in &closing[i + 1..] {
+                    indentation.push_str(token.raw());
+                }
+
+                indentation.push_str(self.indentation.as_deref().unwrap_or("\t"));
+
+                vec![closing[i].clone(), JsonToken::Whitespace(indentation)]
+            }
+            None => closing.to_vec(),
+        }
+    }
+
+    /// Keeps track of how the entry whose value is starting was laid out, taking it from the tokens held in `pending`.
+    fn record_layout(&mut self, is_container: bool) {
+        let mut layout = JsonEntryLayout::default();
+        let mut has_name = false;
+        let mut has_delimiter = false;
+
+        let mut has_leading_delimiter = false;
+
+        for token in &self.pending {
+            match token {
+                JsonToken::ArrayItemDelimiter(_) | JsonToken::PropertyDelimiter(_) => has_leading_delimiter = true,
+                JsonToken::PropertyName { raw: _, name: _ } => has_name = true,
+                JsonToken::KeyValueDelimiter(_) => has_delimiter = true,
+                _ if has_delimiter => layout.value_trivia.push(token.clone()),
+                _ if has_name => layout.name_trivia.push(token.clone()),
+                _ => layout.leading.push(token.clone()),
+            }
+        }
+
+        if has_leading_delimiter && self.delimiter_trivia.is_none() && layout.leading == inline(&layout.leading) {
+            self.delimiter_trivia = Some(layout.leading.clone());
+        }
+
+        if let Some(frame) = self.frames.last_mut() {
+            // Values that are objects or arrays are often put on the next line so a scalar's spacing is preferred.
+            if is_container && has_delimiter {
+                if let Some(value_trivia) = frame.layout.as_ref().map(|previous| &previous.value_trivia).or(self.value_trivia.as_ref()) {
+                    layout.value_trivia = value_trivia.clone();
+                }
+            }
+
+            if has_delimiter && (!is_container || self.value_trivia.is_none()) {
+                self.value_trivia = Some(layout.value_trivia.clone());
+            }
+
+            frame.entries += 1;
+            frame.layout = Some(layout);
+        }
+    }
+
+    fn is_target(&self) -> bool {
+        match self.frames.last() {
+            Some(frame) => frame.is_target,
+            None => false,
+        }
+    }
+
+    fn phase(&self) -> JsonEditPhase {
+        match self.frames.last() {
+            Some(frame) => frame.phase,
+            None => JsonEditPhase::AfterValue,
+        }
+    }
+
+    fn set_phase(&mut self, phase: JsonEditPhase) {
+        if let Some(frame) = self.frames.last_mut() {
+            frame.phase = phase;
+        }
+    }
+
+    fn delimiter(&self) -> JsonToken {
+        match self.edit {
+            JsonEdit::Insert { name: _, value: _ } => JsonToken::PropertyDelimiter(String::from(",")),
+            _ => JsonToken::ArrayItemDelimiter(String::from(",")),
+        }
+    }
+
+    fn set_error(&mut self, error: &str) {
+        if self.error.is_none() {
+            self.error = Some(String::from(error));
+        }
+    }
+
+    fn flush_pending(&mut self) {
+        self.tokens.extend(self.pending.drain(..));
+    }
+
+    fn drop_held_delimiter(&mut self) {
+        if let Some(token) = self.pending.first() {
+            if is_delimiter(token) {
+                self.pending.remove(0);
+            }
+        }
+    }
+}
+
+fn is_delimiter(token: &JsonToken) -> bool {
+    matches!(token, JsonToken::ArrayItemDelimiter(_) | JsonToken::PropertyDelimiter(_))
+}
+
+/// Takes the whitespace that comes before the first new line, if any.
+fn inline(trivia: &[JsonToken]) -> Vec<JsonToken> {
+    trivia.iter().take_while(|token| !matches!(token, JsonToken::NewLine(_))).cloned().collect()
+}
+
+fn pending_name(pending: &[JsonToken]) -> Option<&String> {
+    pending.iter().find_map(|token| match token {
+        JsonToken::PropertyName { raw: _, name } => Some(name),
+        _ => None,
+    })
+}
+
+/// Writes a property name out as a JSON string, escaping the characters that can't appear in one as-is.
+fn quote(name: &str) -> String {
+    quote_with(name, false, false)
+}
+
+/// Writes a new property name out in the same style as the name it replaces, escaping `/` and characters outside of
+/// ASCII if the original did.
+fn requote(name: &str, original: &str) -> String {
+    quote_with(name, original.contains("\\/"), original.contains("\\u"))
+}
+
+fn quote_with(name: &str, escape_solidus: bool, escape_unicode: bool) -> String {
+    let mut raw = String::from("\"");
+
+    for c in name.chars() {
+        match c {
+            '"' => raw.push_str("\\\""),
+            '\\' => raw.push_str("\\\\"),
+            '/' if escape_solidus => raw.push_str("\\/"),
+            '\n' => raw.push_str("\\n"),
+            '\r' => raw.push_str("\\r"),
+            '\t' => raw.push_str("\\t"),
+            c if (c as u32) < 0x20 => raw.push_str(format!("\\u{:04x}", c as u32).as_str()),
+            c if escape_unicode && !c.is_ascii() => {
+                let mut units = [0; 2];
+
+                for unit in c.encode_utf16(&mut units) {
+                    raw.push_str(format!("\\u{:04x}", unit).as_str());
+                }
+            }
+            c => raw.push(c),
+        }
+    }
+
+    raw.push('"');
+    raw
+}
+
+/// Lexes a single JSON literal given on the command line into the tokens that will be written in place of a match.
+///
+/// The lexer rejects anything after the first value but accepts an empty input, so it's left to this to check that there is a value.
+fn tokenize_value(value: &str) -> Result<Vec<JsonToken>, String> {
+    let mut json_lexer = JsonStreamLexer::new();
+    let mut tokens = Vec::new();
+
+    for c in value.chars() {
+        if json_lexer.push_char(c).is_err() {
+            return Err(format!("'{}' is not a valid JSON value", value));
+        }
+
+        while let JsonStreamStatus::Token(token) = json_lexer.pop_token() {
+            tokens.push(token);
+        }
+    }
+
+    if json_lexer.close().is_err() {
+        return Err(format!("'{}' is not a valid JSON value", value));
+    }
+
+    while let JsonStreamStatus::Token(token) = json_lexer.pop_token() {
+        tokens.push(token);
+    }
+
+    let mut depth = 0;
+    let mut values = 0;
+
+    for token in &tokens {
+        match token {
+            JsonToken::ObjectOpen(_) | JsonToken::ArrayOpen(_) => {
+                if depth == 0 {
+                    values += 1;
+                }
+                depth += 1;
+            }
+            JsonToken::ObjectClose(_) | JsonToken::ArrayClose(_) => depth -= 1,
+            JsonToken::Whitespace(_) | JsonToken::NewLine(_) => {}
+            _ => {
+                if depth == 0 {
+                    values += 1;
+                }
+            }
+        }
+    }
+
+    if values != 1 || depth != 0 {
+        return Err(format!("'{}' is not a single JSON value", value));
+    }
+
+    while let Some(JsonToken::Whitespace(_)) = tokens.first() {
+        tokens.remove(0);
+    }
+
+    while let Some(JsonToken::Whitespace(_)) = tokens.last() {
+        tokens.pop();
+    }
+
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TABBED_JSON_SAMPLE: &str = "{\n\t\"id\": \"0001\",\n\t\"ppu\": 0.55,\n\t\"style\": [ \"hole\", \"filled\" ],\n\t\"batters\":\n\t\t{\n\t\t\t\"batter\": [ { \"id\": \"1001\" } ]\n\t\t}\n}";
+
+    fn edit(json: &str, path: &str, edit: JsonEdit) -> String {
+        edit_by(json, &JsonPath::from(path), edit)
+    }
+
+    fn edit_by(json: &str, json_path: &JsonPath, edit: JsonEdit) -> String {
+        try_edit(json, json_path, edit).unwrap()
+    }
+
+    fn edit_error(json: &str, path: &str, edit: JsonEdit) -> String {
+        try_edit(json, &JsonPath::from(path), edit).unwrap_err()
+    }
+
+    fn try_edit(json: &str, json_path: &JsonPath, edit: JsonEdit) -> Result<String, String> {
+        let mut editor = JsonStreamEditor::from(json_path, edit);
+        let mut json_lexer = JsonStreamLexer::new();
+        let mut output = String::new();
+
+        for c in json.chars() {
+            json_lexer.push_char(c).unwrap();
+
+            while let JsonStreamStatus::Token(token) = json_lexer.pop_token() {
+                editor.push_token(token)?;
+            }
+        }
+
+        json_lexer.close().unwrap();
+
+        while let JsonStreamStatus::Token(token) = json_lexer.pop_token() {
+            editor.push_token(token)?;
+        }
+
+        editor.close()?;
+
+        while let JsonStreamStatus::Token(token) = editor.pop_token() {
+            output.push_str(token.raw());
+        }
+
+        Ok(output)
+    }
+
+    #[test]
+    fn test_set_string_member() {
+        assert_eq!(edit(TABBED_JSON_SAMPLE, "$.id", JsonEdit::set("\"0002\"").unwrap()), TABBED_JSON_SAMPLE.replace("\"0001\"", "\"0002\""));
+    }
+
+    #[test]
+    fn test_set_number_member() {
+        assert_eq!(edit(TABBED_JSON_SAMPLE, "$.ppu", JsonEdit::set("1.25").unwrap()), TABBED_JSON_SAMPLE.replace("0.55", "1.25"));
+    }
+
+    #[test]
+    fn test_set_inline_array_items() {
+        assert_eq!(
+            edit(TABBED_JSON_SAMPLE, "$.style[0]", JsonEdit::set("\"ring\"").unwrap()),
+            TABBED_JSON_SAMPLE.replace("\"hole\"", "\"ring\"")
+        );
+        assert_eq!(edit(TABBED_JSON_SAMPLE, "$.style[1]", JsonEdit::set("true").unwrap()), TABBED_JSON_SAMPLE.replace("\"filled\"", "true"));
+    }
+
+    #[test]
+    fn test_set_container_member() {
+        assert_eq!(
+            edit(TABBED_JSON_SAMPLE, "$.batters", JsonEdit::set("{\"batter\": []}").unwrap()),
+            "{\n\t\"id\": \"0001\",\n\t\"ppu\": 0.55,\n\t\"style\": [ \"hole\", \"filled\" ],\n\t\"batters\":\n\t\t{\"batter\": []}\n}"
+        );
+    }
+
+    #[test]
+    fn test_set_nested_member() {
+        assert_eq!(
+            edit(TABBED_JSON_SAMPLE, "$.batters.batter[0].id", JsonEdit::set("null").unwrap()),
+            TABBED_JSON_SAMPLE.replace("\"1001\"", "null")
+        );
+    }
+
+    #[test]
+    fn test_set_invalid_value() {
+        assert!(JsonEdit::set("1, 2").is_err());
+        assert!(JsonEdit::set("{\"a\": 1").is_err());
+        assert!(JsonEdit::set("").is_err());
+    }
+
+    #[test]
+    fn test_delete_first_member() {
+        assert_eq!(
+            edit(TABBED_JSON_SAMPLE, "$.id", JsonEdit::Delete),
+            "{\n\t\"ppu\": 0.55,\n\t\"style\": [ \"hole\", \"filled\" ],\n\t\"batters\":\n\t\t{\n\t\t\t\"batter\": [ { \"id\": \"1001\" } ]\n\t\t}\n}"
+        );
+    }
+
+    #[test]
+    fn test_delete_middle_member() {
+        assert_eq!(edit(TABBED_JSON_SAMPLE, "$.ppu", JsonEdit::Delete), TABBED_JSON_SAMPLE.replace("\t\"ppu\": 0.55,\n", ""));
+    }
+
+    #[test]
+    fn test_delete_last_member() {
+        assert_eq!(
+            edit(TABBED_JSON_SAMPLE, "$.batters", JsonEdit::Delete),
+            "{\n\t\"id\": \"0001\",\n\t\"ppu\": 0.55,\n\t\"style\": [ \"hole\", \"filled\" ]\n}"
+        );
+    }
+
+    #[test]
+    fn test_delete_inline_array_items() {
+        assert_eq!(
+            edit(TABBED_JSON_SAMPLE, "$.style[0]", JsonEdit::Delete),
+            TABBED_JSON_SAMPLE.replace("[ \"hole\", \"filled\" ]", "[ \"filled\" ]")
+        );
+        assert_eq!(
+            edit(TABBED_JSON_SAMPLE, "$.style[1]", JsonEdit::Delete),
+            TABBED_JSON_SAMPLE.replace("[ \"hole\", \"filled\" ]", "[ \"hole\" ]")
+        );
+    }
+
+    #[test]
+    fn test_delete_compact_first_entries() {
+        assert_eq!(edit("[1, 2, 3]", "$[0]", JsonEdit::Delete), "[2, 3]");
+        assert_eq!(edit("[1, 2, 3]", "$[0:2]", JsonEdit::Delete), "[3]");
+        assert_eq!(edit("[1,2,3]", "$[0]", JsonEdit::Delete), "[2,3]");
+        assert_eq!(edit("{\"a\": 1, \"b\": 2}", "$.a", JsonEdit::Delete), "{\"b\": 2}");
+        assert_eq!(edit("{\"a\": {\"b\": 1, \"c\": 2}}", "$.a.b", JsonEdit::Delete), "{\"a\": {\"c\": 2}}");
+        assert_eq!(edit("[ 1,\n  2 ]", "$[0]", JsonEdit::Delete), "[ 2 ]");
+    }
+
+    #[test]
+    fn test_delete_root() {
+        assert_eq!(edit_error("{\"a\": 1}", "$", JsonEdit::Delete), "the whole document can't be deleted");
+        assert_eq!(edit_error("[1]", "$", JsonEdit::Delete), "the whole document can't be deleted");
+    }
+
+    #[test]
+    fn test_delete_array_slices() {
+        let json = "{ \"a\": [ 0, 1, 2, 3, 4 ] }";
+
+        assert_eq!(edit(json, "$.a[1:3]", JsonEdit::Delete), "{ \"a\": [ 0, 3, 4 ] }");
+        assert_eq!(edit(json, "$.a[-2:]", JsonEdit::Delete), "{ \"a\": [ 0, 1, 2 ] }");
+        assert_eq!(edit(json, "$.a[::2]", JsonEdit::Delete), "{ \"a\": [ 1, 3 ] }");
+        assert_eq!(edit(json, "$.a[:-1]", JsonEdit::set("9").unwrap()), "{ \"a\": [ 9, 9, 9, 9, 4 ] }");
+    }
+
+    #[test]
+    fn test_deep_scan_edits() {
+        let json = "{ \"db\": { \"user\": \"a\", \"password\": \"b\" }, \"replicas\": [ { \"password\": \"c\" } ] }";
+
+        assert_eq!(
+            edit(json, "$..password", JsonEdit::set("\"*\"").unwrap()),
+            "{ \"db\": { \"user\": \"a\", \"password\": \"*\" }, \"replicas\": [ { \"password\": \"*\" } ] }"
+        );
+        assert_eq!(edit(json, "$..password", JsonEdit::Delete), "{ \"db\": { \"user\": \"a\" }, \"replicas\": [ { } ] }");
+    }
+
+    #[test]
+    fn test_wildcard_edits() {
+        let json = "{ \"a\": [ 1, 2 ], \"b\": { \"c\": 3, \"d\": [ 4 ] } }";
+
+        assert_eq!(edit(json, "$.a[*]", JsonEdit::set("0").unwrap()), "{ \"a\": [ 0, 0 ], \"b\": { \"c\": 3, \"d\": [ 4 ] } }");
+        assert_eq!(edit(json, "$.b.*", JsonEdit::Delete), "{ \"a\": [ 1, 2 ], \"b\": { } }");
+    }
+
+    #[test]
+    fn test_union_edits() {
+        let json = "{ \"a\": [ 1, 2, 3, 4 ], \"b\": 5, \"c\": 6 }";
+
+        assert_eq!(edit(json, "$.a[3,0]", JsonEdit::Delete), "{ \"a\": [ 2, 3 ], \"b\": 5, \"c\": 6 }");
+        assert_eq!(edit(json, "$['c','b']", JsonEdit::set("0").unwrap()), "{ \"a\": [ 1, 2, 3, 4 ], \"b\": 0, \"c\": 0 }");
+    }
+
+    #[test]
+    fn test_json_pointer_edits() {
+        let json = "{ \"a\": [ 1, 2 ], \"b/c\": 3 }";
+
+        assert_eq!(edit_by(json, &JsonPath::parse_pointer("/a/0").unwrap(), JsonEdit::Delete), "{ \"a\": [ 2 ], \"b/c\": 3 }");
+        assert_eq!(
+            edit_by(json, &JsonPath::parse_pointer("/b~1c").unwrap(), JsonEdit::set("4").unwrap()),
+            "{ \"a\": [ 1, 2 ], \"b/c\": 4 }"
+        );
+    }
+
+    #[test]
+    fn test_negative_index_edits() {
+        let json = "{ \"a\": [ 1, 2, 3 ] }";
+
+        assert_eq!(edit(json, "$.a[-1]", JsonEdit::Delete), "{ \"a\": [ 1, 2 ] }");
+        assert_eq!(edit(json, "$.a[-3]", JsonEdit::set("0").unwrap()), "{ \"a\": [ 0, 2, 3 ] }");
+    }
+
+    #[test]
+    fn test_filter_edits() {
+        let json = "{ \"users\": [ { \"name\": \"a\", \"admin\": true }, { \"name\": \"b\" }, { \"name\": \"c\", \"admin\": false } ] }";
+
+        assert_eq!(
+            edit(json, "$.users[?(@.admin == true)].name", JsonEdit::set("\"root\"").unwrap()),
+            "{ \"users\": [ { \"name\": \"root\", \"admin\": true }, { \"name\": \"b\" }, { \"name\": \"c\", \"admin\": false } ] }"
+        );
+        assert_eq!(
+            edit(json, "$.users[?(!@.admin)]", JsonEdit::Delete),
+            "{ \"users\": [ { \"name\": \"a\", \"admin\": true }, { \"name\": \"c\", \"admin\": false } ] }"
+        );
+    }
+
+    #[test]
+    fn test_delete_only_array_item() {
+        assert_eq!(
+            edit(TABBED_JSON_SAMPLE, "$.batters.batter[0]", JsonEdit::Delete),
+            TABBED_JSON_SAMPLE.replace("[ { \"id\": \"1001\" } ]", "[ ]")
+        );
+    }
+
+    #[test]
+    fn test_delete_vertical_array_items() {
+        let json = "[\n    { \"id\": \"5001\" },\n    { \"id\": \"5002\" },\n    { \"id\": \"5005\" }\n]";
+
+        assert_eq!(edit(json, "$[0]", JsonEdit::Delete), "[\n    { \"id\": \"5002\" },\n    { \"id\": \"5005\" }\n]");
+        assert_eq!(edit(json, "$[1]", JsonEdit::Delete), "[\n    { \"id\": \"5001\" },\n    { \"id\": \"5005\" }\n]");
+        assert_eq!(edit(json, "$[2]", JsonEdit::Delete), "[\n    { \"id\": \"5001\" },\n    { \"id\": \"5002\" }\n]");
+    }
+
+    #[test]
+    fn test_insert_multi_line_tabs() {
+        assert_eq!(
+            edit(TABBED_JSON_SAMPLE, "$.batters", JsonEdit::insert("name", "\"Cake\"").unwrap()),
+            TABBED_JSON_SAMPLE.replace("} ]\n", "} ],\n\t\t\t\"name\": \"Cake\"\n")
+        );
+    }
+
+    #[test]
+    fn test_insert_multi_line_spaces() {
+        let json = "{\n    \"topping\":\n    {\n        \"id\": \"5001\",\n        \"type\": \"None\"\n    }\n}";
+
+        assert_eq!(
+            edit(json, "$.topping", JsonEdit::insert("price", "0.5").unwrap()),
+            "{\n    \"topping\":\n    {\n        \"id\": \"5001\",\n        \"type\": \"None\",\n        \"price\": 0.5\n    }\n}"
+        );
+    }
+
+    #[test]
+    fn test_insert_single_line() {
+        assert_eq!(
+            edit(TABBED_JSON_SAMPLE, "$.batters.batter[0]", JsonEdit::insert("type", "\"Regular\"").unwrap()),
+            TABBED_JSON_SAMPLE.replace("{ \"id\": \"1001\" }", "{ \"id\": \"1001\", \"type\": \"Regular\" }")
+        );
+    }
+
+    #[test]
+    fn test_insert_empty_objects() {
+        assert_eq!(edit("{\"a\":1,\"b\":{}}", "$.b", JsonEdit::insert("c", "2").unwrap()), "{\"a\":1,\"b\":{\"c\":2}}");
+        assert_eq!(
+            edit("{\n\t\"a\": 1,\n\t\"b\": { }\n}", "$.b", JsonEdit::insert("c", "2").unwrap()),
+            "{\n\t\"a\": 1,\n\t\"b\": { \"c\": 2 }\n}"
+        );
+        assert_eq!(
+            edit("{\n\t\"a\": 1,\n\t\"b\": {\n\t}\n}", "$.b", JsonEdit::insert("c", "2").unwrap()),
+            "{\n\t\"a\": 1,\n\t\"b\": {\n\t\t\"c\": 2\n\t}\n}"
+        );
+    }
+
+    #[test]
+    fn test_insert_existing_member() {
+        assert_eq!(
+            edit(TABBED_JSON_SAMPLE, "$.batters", JsonEdit::insert("batter", "[]").unwrap()),
+            TABBED_JSON_SAMPLE.replace("[ { \"id\": \"1001\" } ]", "[]")
+        );
+    }
+
+    #[test]
+    fn test_insert_escaped_name() {
+        assert_eq!(
+            edit("{\"o\": {\"a\": 1}}", "$.o", JsonEdit::insert("say \"hi\"", "2").unwrap()),
+            "{\"o\": {\"a\": 1, \"say \\\"hi\\\"\": 2}}"
+        );
+    }
+
+    #[test]
+    fn test_insert_into_non_object() {
+        let error = "a member can only be inserted into an object";
+
+        assert_eq!(edit_error(TABBED_JSON_SAMPLE, "$.style", JsonEdit::insert("a", "1").unwrap()), error);
+        assert_eq!(edit_error("{\"a\": 1}", "$.a", JsonEdit::insert("b", "2").unwrap()), error);
+        assert_eq!(edit_error("\"a\"", "$", JsonEdit::insert("b", "2").unwrap()), error);
+        assert_eq!(edit_error("{\"a\": {}, \"b\": null}", "$.*", JsonEdit::insert("c", "3").unwrap()), error);
+    }
+
+    #[test]
+    fn test_append_inline_array() {
+        assert_eq!(
+            edit(TABBED_JSON_SAMPLE, "$.style", JsonEdit::append("\"glazed\"").unwrap()),
+            TABBED_JSON_SAMPLE.replace("[ \"hole\", \"filled\" ]", "[ \"hole\", \"filled\", \"glazed\" ]")
+        );
+        assert_eq!(
+            edit(TABBED_JSON_SAMPLE, "$.batters.batter", JsonEdit::append("{ \"id\": \"1002\" }").unwrap()),
+            TABBED_JSON_SAMPLE.replace("[ { \"id\": \"1001\" } ]", "[ { \"id\": \"1001\" }, { \"id\": \"1002\" } ]")
+        );
+    }
+
+    #[test]
+    fn test_prepend_inline_array() {
+        assert_eq!(
+            edit(TABBED_JSON_SAMPLE, "$.style", JsonEdit::prepend("\"glazed\"").unwrap()),
+            TABBED_JSON_SAMPLE.replace("[ \"hole\", \"filled\" ]", "[ \"glazed\", \"hole\", \"filled\" ]")
+        );
+        assert_eq!(edit("{\"a\": [1, 2]}", "$.a", JsonEdit::prepend("0").unwrap()), "{\"a\": [0, 1, 2]}");
+    }
+
+    #[test]
+    fn test_append_prepend_vertical_array() {
+        let json = "{\n    \"topping\":\n    [\n        { \"id\": \"5001\" },\n        { \"id\": \"5002\" }\n    ]\n}";
+
+        assert_eq!(
+            edit(json, "$.topping", JsonEdit::append("{ \"id\": \"5005\" }").unwrap()),
+            "{\n    \"topping\":\n    [\n        { \"id\": \"5001\" },\n        { \"id\": \"5002\" },\n        { \"id\": \"5005\" }\n    ]\n}"
+        );
+        assert_eq!(
+            edit(json, "$.topping", JsonEdit::prepend("{ \"id\": \"5000\" }").unwrap()),
+            "{\n    \"topping\":\n    [\n        { \"id\": \"5000\" },\n        { \"id\": \"5001\" },\n        { \"id\": \"5002\" }\n    ]\n}"
+        );
+    }
+
+    #[test]
+    fn test_append_prepend_empty_arrays() {
+        assert_eq!(edit("{\"a\":[]}", "$.a", JsonEdit::append("1").unwrap()), "{\"a\":[1]}");
+        assert_eq!(edit("{\"a\": [ ]}", "$.a", JsonEdit::prepend("1").unwrap()), "{\"a\": [ 1 ]}");
+        assert_eq!(edit("{\n\t\"a\": [\n\t]\n}", "$.a", JsonEdit::append("1").unwrap()), "{\n\t\"a\": [\n\t\t1\n\t]\n}");
+        assert_eq!(edit("{\n\t\"a\": [\n\t]\n}", "$.a", JsonEdit::prepend("1").unwrap()), "{\n\t\"a\": [\n\t\t1\n\t]\n}");
+    }
+
+    #[test]
+    fn test_append_prepend_non_array() {
+        let error = "an item can only be added to an array";
+
+        assert_eq!(edit_error(TABBED_JSON_SAMPLE, "$.batters", JsonEdit::append("1").unwrap()), error);
+        assert_eq!(edit_error(TABBED_JSON_SAMPLE, "$.batters", JsonEdit::prepend("1").unwrap()), error);
+        assert_eq!(edit_error("{\"a\": \"x\"}", "$.a", JsonEdit::append("1").unwrap()), error);
+        assert_eq!(edit_error("1", "$", JsonEdit::prepend("0").unwrap()), error);
+    }
+
+    #[test]
+    fn test_append_single_item_array() {
+        assert_eq!(edit("{\"a\": [1], \"b\": 2}", "$.a", JsonEdit::append("2").unwrap()), "{\"a\": [1, 2], \"b\": 2}");
+        assert_eq!(edit("{\"a\":[1]}", "$.a", JsonEdit::append("2").unwrap()), "{\"a\":[1,2]}");
+    }
+
+    #[test]
+    fn test_rename_member() {
+        assert_eq!(
+            edit(TABBED_JSON_SAMPLE, "$.batters", JsonEdit::Rename(String::from("batter_types"))),
+            TABBED_JSON_SAMPLE.replace("\"batters\"", "\"batter_types\"")
+        );
+        assert_eq!(
+            edit(TABBED_JSON_SAMPLE, "$.batters.batter[0].id", JsonEdit::Rename(String::from("key"))),
+            TABBED_JSON_SAMPLE.replace("{ \"id\"", "{ \"key\"")
+        );
+    }
+
+    #[test]
+    fn test_rename_keeps_escaping() {
+        assert_eq!(requote("c/dé", "\"a\\/b\\u00e9\""), "\"c\\/d\\u00e9\"");
+        assert_eq!(requote("c/dé", "\"a\""), "\"c/dé\"");
+        assert_eq!(edit("{\"a\": 1}", "$.a", JsonEdit::Rename(String::from("a/\"b\""))), "{\"a/\\\"b\\\"\": 1}");
+    }
+
+    #[test]
+    fn test_rename_clash() {
+        let error = "a member can't be renamed to 'b' as the object already has one with that name";
+
+        assert_eq!(edit_error("{\"a\":1,\"b\":2}", "$.a", JsonEdit::Rename(String::from("b"))), error);
+        assert_eq!(edit_error("{\"b\":1,\"a\":2}", "$.a", JsonEdit::Rename(String::from("b"))), error);
+        assert_eq!(edit_error("{\"a\":1,\"c\":2}", "$.*", JsonEdit::Rename(String::from("b"))), error);
+        assert_eq!(edit("{\"a\":1,\"c\":{\"b\":2}}", "$.a", JsonEdit::Rename(String::from("b"))), "{\"b\":1,\"c\":{\"b\":2}}");
+        assert_eq!(edit("{\"a\":1,\"b\":2}", "$.a", JsonEdit::Rename(String::from("a"))), "{\"a\":1,\"b\":2}");
+        assert_eq!(edit("[{\"a\":1},{\"b\":2}]", "$[0].a", JsonEdit::Rename(String::from("b"))), "[{\"b\":1},{\"b\":2}]");
+    }
+
+    #[test]
+    fn test_rename_array_item() {
+        let error = "only a member of an object can be renamed";
+
+        assert_eq!(edit_error(TABBED_JSON_SAMPLE, "$.style[0]", JsonEdit::Rename(String::from("a"))), error);
+        assert_eq!(edit_error(TABBED_JSON_SAMPLE, "$.batters.batter[*]", JsonEdit::Rename(String::from("a"))), error);
+        assert_eq!(edit_error(TABBED_JSON_SAMPLE, "$", JsonEdit::Rename(String::from("a"))), error);
+        assert_eq!(edit_error("1", "$", JsonEdit::Rename(String::from("a"))), error);
+    }
+}
@@ -0,0 +1,831 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+use super::{
+    lexer::JsonToken,
+    path::{JsonPath, JsonPathOperator},
+    query::{JsonQuery, JsonQueryStatus},
+};
+
+/// A filter expression from between the brackets of `[?...]`, e.g. `@.price < 10 && @.category == 'fiction'`.
+///
+/// The paths in a filter are relative to the value being filtered and are kept separately, with the expression referring
+/// to them by their index.
+pub struct JsonFilter {
+    source: String,
+    expression: JsonFilterExpression,
+    paths: Vec<JsonPath>,
+}
+
+enum JsonFilterExpression {
+    Or(Box<JsonFilterExpression>, Box<JsonFilterExpression>),
+    And(Box<JsonFilterExpression>, Box<JsonFilterExpression>),
+    Not(Box<JsonFilterExpression>),
+    Comparison {
+        left: JsonFilterOperand,
+        operator: JsonComparison,
+        right: JsonFilterOperand,
+    },
+    Exists(usize),
+}
+
+enum JsonFilterOperand {
+    Path(usize),
+    Literal(JsonValue),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum JsonComparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+/// A whole JSON value, built up from its tokens so that a filter can compare it.
+#[derive(Clone, Debug, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Boolean(bool),
+    Number(f64),
+    /// An integer with too many digits for an `f64` to hold exactly, kept as its digits without leading zeros and with a
+    /// `-` if it's negative so that it can be compared exactly.
+    BigInteger(String),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+/// Writes the value out as compact JSON.
+impl fmt::Display for JsonValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonValue::Null => write!(f, "null"),
+            JsonValue::Boolean(value) => write!(f, "{}", value),
+            JsonValue::Number(value) => write!(f, "{}", value),
+            JsonValue::BigInteger(value) => write!(f, "{}", value),
+            JsonValue::String(value) => write_string(f, value),
+            JsonValue::Array(items) => {
+                write!(f, "[")?;
+
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+
+                    write!(f, "{}", item)?;
+                }
+
+                write!(f, "]")
+            }
+            JsonValue::Object(members) => {
+                write!(f, "{{")?;
+
+                for (i, (name, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+
+                    write_string(f, name)?;
+                    write!(f, ":{}", value)?;
+                }
+
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, value: &str) -> fmt::Result {
+    write!(f, "\"")?;
+
+    for c in value.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+
+    write!(f, "\"")
+}
+
+/// What a filter's path has turned up in the value being filtered so far.
+enum JsonFilterNode {
+    Unknown,
+    Nothing,
+    Value(JsonValue),
+}
+
+/// Where a filter expression stopped making sense, counted in characters from the start of the expression.
+#[derive(Debug, PartialEq)]
+pub struct JsonFilterError {
+    pub position: usize,
+    pub expected: String,
+}
+
+impl fmt::Display for JsonFilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "expected {} at position {} of the filter", self.expected, self.position)
+    }
+}
+
+impl JsonFilter {
+    pub fn parse(expression: &str) -> Result<JsonFilter, JsonFilterError> {
+        let mut parser = JsonFilterParser {
+            chars: expression.chars().collect(),
+            position: 0,
+            paths: Vec::new(),
+        };
+
+        let parsed = parser.parse_or()?;
+
+        parser.skip_whitespace();
+
+        if parser.position < parser.chars.len() {
+            return Err(parser.error("'&&', '||' or the end of the filter"));
+        }
+
+        Ok(JsonFilter {
+            source: String::from(unwrap_parentheses(expression.trim())),
+            expression: parsed,
+            paths: parser.paths,
+        })
+    }
+}
+
+impl fmt::Display for JsonFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+/// Takes off a pair of parentheses around the whole expression, as in the older `[?(...)]` form of filters.
+fn unwrap_parentheses(expression: &str) -> &str {
+    if !expression.starts_with('(') || !expression.ends_with(')') {
+        return expression;
+    }
+
+    let mut depth = 0;
+    let mut quote = None;
+    let mut is_escaped = false;
+
+    for (i, c) in expression.char_indices() {
+        match (quote, c) {
+            (Some(_), _) if is_escaped => is_escaped = false,
+            (Some(_), '\\') => is_escaped = true,
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') => {
+                depth -= 1;
+
+                // The opening parenthesis is closed before the end, as in `(@.a) && (@.b)`.
+                if depth == 0 && i < expression.len() - 1 {
+                    return expression;
+                }
+            }
+            (None, _) => {}
+        }
+    }
+
+    &expression[1..expression.len() - 1]
+}
+
+struct JsonFilterParser {
+    chars: Vec<char>,
+    position: usize,
+    paths: Vec<JsonPath>,
+}
+
+impl JsonFilterParser {
+    fn parse_or(&mut self) -> Result<JsonFilterExpression, JsonFilterError> {
+        let mut expression = self.parse_and()?;
+
+        while self.next_is("||") {
+            self.position += 2;
+            expression = JsonFilterExpression::Or(Box::new(expression), Box::new(self.parse_and()?));
+        }
+
+        Ok(expression)
+    }
+
+    fn parse_and(&mut self) -> Result<JsonFilterExpression, JsonFilterError> {
+        let mut expression = self.parse_not()?;
+
+        while self.next_is("&&") {
+            self.position += 2;
+            expression = JsonFilterExpression::And(Box::new(expression), Box::new(self.parse_not()?));
+        }
+
+        Ok(expression)
+    }
+
+    fn parse_not(&mut self) -> Result<JsonFilterExpression, JsonFilterError> {
+        if self.next_is("!") && !self.next_is("!=") {
+            self.position += 1;
+            return Ok(JsonFilterExpression::Not(Box::new(self.parse_not()?)));
+        }
+
+        if self.next_is("(") {
+            self.position += 1;
+
+            let expression = self.parse_or()?;
+
+            if !self.next_is(")") {
+                return Err(self.error("')'"));
+            }
+
+            self.position += 1;
+
+            return Ok(expression);
+        }
+
+        self.skip_whitespace();
+
+        let left_start = self.position;
+        let left = self.parse_operand()?;
+
+        let operator = match self.parse_comparison() {
+            Some(operator) => operator,
+            None => {
+                return match left {
+                    JsonFilterOperand::Path(path) => Ok(JsonFilterExpression::Exists(path)),
+                    JsonFilterOperand::Literal(_) => Err(self.error("a comparison such as '==' or '<'")),
+                }
+            }
+        };
+
+        self.skip_whitespace();
+
+        let right_start = self.position;
+        let right = self.parse_operand()?;
+
+        self.check_singular(&left, left_start)?;
+        self.check_singular(&right, right_start)?;
+
+        Ok(JsonFilterExpression::Comparison { left, operator, right })
+    }
+
+    /// Checks that a path being compared can only find a single value, as RFC 9535 requires, which rules out wildcards,
+    /// slices, filters, unions and deep scans. A path on its own only tests whether it finds anything so it can have them.
+    fn check_singular(&self, operand: &JsonFilterOperand, start: usize) -> Result<(), JsonFilterError> {
+        let path = match operand {
+            JsonFilterOperand::Path(path) => &self.paths[*path],
+            JsonFilterOperand::Literal(_) => return Ok(()),
+        };
+
+        match path
+            .operations()
+            .iter()
+            .skip(1)
+            .all(|operator| matches!(operator, JsonPathOperator::MemberAccess(_) | JsonPathOperator::ArrayIndex(_)))
+        {
+            true => Ok(()),
+            false => Err(JsonFilterError {
+                position: start,
+                expected: String::from("a path to a single value, with only member names and indices, to compare"),
+            }),
+        }
+    }
+
+    fn parse_comparison(&mut self) -> Option<JsonComparison> {
+        let operators = [
+            ("==", JsonComparison::Equal),
+            ("!=", JsonComparison::NotEqual),
+            ("<=", JsonComparison::LessOrEqual),
+            (">=", JsonComparison::GreaterOrEqual),
+            ("<", JsonComparison::Less),
+            (">", JsonComparison::Greater),
+        ];
+
+        for (symbol, operator) in operators {
+            if self.next_is(symbol) {
+                self.position += symbol.chars().count();
+                return Some(operator);
+            }
+        }
+
+        None
+    }
+
+    fn parse_operand(&mut self) -> Result<JsonFilterOperand, JsonFilterError> {
+        self.skip_whitespace();
+
+        match self.chars.get(self.position) {
+            Some('@') => self.parse_path(),
+            Some('$') => Err(self.error("a path starting with '@', paths from the root can't be used in filters")),
+            Some('\'' | '"') => Ok(JsonFilterOperand::Literal(JsonValue::String(self.parse_string()?))),
+            Some(c) if *c == '-' || c.is_ascii_digit() => self.parse_number(),
+            Some(_) => {
+                for (keyword, value) in [("true", JsonValue::Boolean(true)), ("false", JsonValue::Boolean(false)), ("null", JsonValue::Null)] {
+                    if self.next_is(keyword) {
+                        self.position += keyword.len();
+                        return Ok(JsonFilterOperand::Literal(value));
+                    }
+                }
+
+                Err(self.error("a path or a value"))
+            }
+            None => Err(self.error("a path or a value")),
+        }
+    }
+
+    /// Reads a path relative to the value being filtered, made up of members, deep scans and anything in brackets.
+    fn parse_path(&mut self) -> Result<JsonFilterOperand, JsonFilterError> {
+        let start = self.position;
+        let mut path = String::from("$");
+
+        self.position += 1;
+
+        while let Some(c) = self.chars.get(self.position) {
+            match c {
+                '.' | '_' | '-' | '*' => self.position += 1,
+                c if c.is_alphanumeric() => self.position += 1,
+                '[' => self.skip_brackets()?,
+                _ => break,
+            }
+        }
+
+        path.extend(&self.chars[start + 1..self.position]);
+
+        // The `$` stands in for the `@`, so a position in the path is the same distance from the start of this one.
+        match JsonPath::parse(path.as_str()) {
+            Ok(path) => self.paths.push(path),
+            Err(err) => {
+                return Err(JsonFilterError {
+                    position: start + err.position,
+                    expected: err.expected,
+                })
+            }
+        }
+
+        Ok(JsonFilterOperand::Path(self.paths.len() - 1))
+    }
+
+    /// Skips over a bracketed part of a path, along with any brackets nested in it such as those of a filter.
+    fn skip_brackets(&mut self) -> Result<(), JsonFilterError> {
+        let mut depth = 0;
+        let mut quote = None;
+        let mut is_escaped = false;
+
+        while let Some(c) = self.chars.get(self.position) {
+            self.position += 1;
+
+            match (quote, *c) {
+                (Some(_), _) if is_escaped => is_escaped = false,
+                (Some(_), '\\') => is_escaped = true,
+                (Some(q), c) if c == q => quote = None,
+                (Some(_), _) => {}
+                (None, '\'' | '"') => quote = Some(*c),
+                (None, '[') => depth += 1,
+                (None, ']') if depth == 1 => return Ok(()),
+                (None, ']') => depth -= 1,
+                (None, _) => {}
+            }
+        }
+
+        Err(self.error("']'"))
+    }
+
+    fn parse_string(&mut self) -> Result<String, JsonFilterError> {
+        let quote = self.chars[self.position];
+        let mut value = String::new();
+
+        self.position += 1;
+
+        while let Some(c) = self.chars.get(self.position) {
+            self.position += 1;
+
+            match *c {
+                c if c == quote => return Ok(value),
+                '\\' => {
+                    let escape = match self.chars.get(self.position) {
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => {
+                            let hex: String = self.chars.iter().skip(self.position + 1).take(4).collect();
+
+                            match u32::from_str_radix(hex.as_str(), 16).ok().and_then(char::from_u32) {
+                                Some(c) if hex.len() == 4 => {
+                                    self.position += 4;
+                                    c
+                                }
+                                _ => return Err(self.error("four hexadecimal digits")),
+                            }
+                        }
+                        Some(c) => *c,
+                        None => return Err(self.error("an escape character")),
+                    };
+
+                    self.position += 1;
+                    value.push(escape);
+                }
+                c => value.push(c),
+            }
+        }
+
+        Err(self.error(format!("a closing {}", quote).as_str()))
+    }
+
+    fn parse_number(&mut self) -> Result<JsonFilterOperand, JsonFilterError> {
+        let start = self.position;
+
+        while let Some(c) = self.chars.get(self.position) {
+            match c {
+                '0'..='9' | '-' | '+' | '.' | 'e' | 'E' => self.position += 1,
+                _ => break,
+            }
+        }
+
+        let number: String = self.chars[start..self.position].iter().collect();
+
+        match number_value(number.as_str()) {
+            Some(number) => Ok(JsonFilterOperand::Literal(number)),
+            None => {
+                self.position = start;
+                Err(self.error("a number"))
+            }
+        }
+    }
+
+    /// Skips any whitespace and then checks whether the expression carries on with `symbol`.
+    fn next_is(&mut self, symbol: &str) -> bool {
+        self.skip_whitespace();
+
+        let mut chars = self.chars[self.position..].iter();
+
+        symbol.chars().all(|c| chars.next() == Some(&c))
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.chars.get(self.position) {
+            if !c.is_whitespace() {
+                break;
+            }
+
+            self.position += 1;
+        }
+    }
+
+    fn error(&self, expected: &str) -> JsonFilterError {
+        JsonFilterError {
+            position: self.position,
+            expected: String::from(expected),
+        }
+    }
+}
+
+/// Runs a filter over the tokens of a single value, deciding whether the value is selected as soon as enough of it has
+/// gone past.
+pub struct JsonFilterRun<'a> {
+    filter: &'a JsonFilter,
+    queries: Vec<JsonQuery<'a>>,
+    builders: Vec<JsonValueBuilder>,
+    nodes: Vec<JsonFilterNode>,
+}
+
+impl<'a> JsonFilterRun<'a> {
+    pub fn from(filter: &'a JsonFilter) -> JsonFilterRun<'a> {
+        JsonFilterRun {
+            filter,
+            queries: filter.paths.iter().map(JsonQuery::from).collect(),
+            builders: filter.paths.iter().map(|_| JsonValueBuilder::new()).collect(),
+            nodes: filter.paths.iter().map(|_| JsonFilterNode::Unknown).collect(),
+        }
+    }
+
+    pub fn push_token(&mut self, token: &JsonToken) {
+        for i in 0..self.queries.len() {
+            if let JsonFilterNode::Unknown = self.nodes[i] {
+                self.queries[i].push_token(token.clone());
+                self.pop_query_tokens(i);
+            }
+        }
+    }
+
+    /// Finishes the run at the end of the value. Paths that haven't turned anything up by now never will.
+    pub fn close(&mut self) {
+        for i in 0..self.queries.len() {
+            self.queries[i].close();
+            self.pop_query_tokens(i);
+
+            if let JsonFilterNode::Unknown = self.nodes[i] {
+                self.nodes[i] = JsonFilterNode::Nothing;
+            }
+        }
+    }
+
+    /// Works out whether the value is selected, or `None` if that depends on parts of the value still to come.
+    pub fn decide(&self) -> Option<bool> {
+        evaluate(&self.filter.expression, &self.nodes)
+    }
+
+    fn pop_query_tokens(&mut self, i: usize) {
+        while let JsonQueryStatus::Token { token, capture, .. } = self.queries[i].pop_token() {
+            if !capture {
+                continue;
+            }
+
+            // Only the first value is kept, a path that matches several values is only ever tested for existence.
+            if let (Some(value), JsonFilterNode::Unknown) = (self.builders[i].push_token(&token), &self.nodes[i]) {
+                self.nodes[i] = JsonFilterNode::Value(value);
+            }
+        }
+    }
+}
+
+/// Works out an expression, or `None` if a path it depends on hasn't been found yet.
+fn evaluate(expression: &JsonFilterExpression, nodes: &[JsonFilterNode]) -> Option<bool> {
+    match expression {
+        JsonFilterExpression::Or(left, right) => match (evaluate(left, nodes), evaluate(right, nodes)) {
+            (Some(true), _) | (_, Some(true)) => Some(true),
+            (Some(false), Some(false)) => Some(false),
+            _ => None,
+        },
+        JsonFilterExpression::And(left, right) => match (evaluate(left, nodes), evaluate(right, nodes)) {
+            (Some(false), _) | (_, Some(false)) => Some(false),
+            (Some(true), Some(true)) => Some(true),
+            _ => None,
+        },
+        JsonFilterExpression::Not(expression) => evaluate(expression, nodes).map(|is_met| !is_met),
+        JsonFilterExpression::Exists(path) => match &nodes[*path] {
+            JsonFilterNode::Unknown => None,
+            JsonFilterNode::Nothing => Some(false),
+            JsonFilterNode::Value(_) => Some(true),
+        },
+        JsonFilterExpression::Comparison { left, operator, right } => Some(compare(operand(left, nodes)?, *operator, operand(right, nodes)?)),
+    }
+}
+
+/// Looks up the value of an operand. The outer `Option` is `None` if it isn't known yet, the inner one if there is nothing.
+fn operand<'b>(operand: &'b JsonFilterOperand, nodes: &'b [JsonFilterNode]) -> Option<Option<&'b JsonValue>> {
+    match operand {
+        JsonFilterOperand::Literal(value) => Some(Some(value)),
+        JsonFilterOperand::Path(path) => match &nodes[*path] {
+            JsonFilterNode::Unknown => None,
+            JsonFilterNode::Nothing => Some(None),
+            JsonFilterNode::Value(value) => Some(Some(value)),
+        },
+    }
+}
+
+/// Compares two values the way RFC 9535 does. Only numbers and strings can be ordered and a path that found nothing is
+/// only equal to another that found nothing.
+fn compare(left: Option<&JsonValue>, operator: JsonComparison, right: Option<&JsonValue>) -> bool {
+    match operator {
+        JsonComparison::Equal => match (left, right) {
+            (None, None) => true,
+            (Some(left), Some(right)) => equals(left, right),
+            _ => false,
+        },
+        JsonComparison::NotEqual => !compare(left, JsonComparison::Equal, right),
+        JsonComparison::Less => match (left, right) {
+            (Some(JsonValue::String(left)), Some(JsonValue::String(right))) => left < right,
+            (Some(left), Some(right)) => compare_numbers(left, right) == Some(Ordering::Less),
+            _ => false,
+        },
+        JsonComparison::LessOrEqual => compare(left, JsonComparison::Less, right) || compare(left, JsonComparison::Equal, right),
+        JsonComparison::Greater => compare(right, JsonComparison::Less, left),
+        JsonComparison::GreaterOrEqual => compare(right, JsonComparison::LessOrEqual, left),
+    }
+}
+
+/// Objects are equal if they have the same members, in any order.
+fn equals(left: &JsonValue, right: &JsonValue) -> bool {
+    match (left, right) {
+        (JsonValue::Array(left), JsonValue::Array(right)) => left.len() == right.len() && left.iter().zip(right).all(|(left, right)| equals(left, right)),
+        (JsonValue::Object(left), JsonValue::Object(right)) => left.len() == right.len() && left.iter().all(|(name, left)| right.iter().any(|(other, right)| name == other && equals(left, right))),
+        (JsonValue::Number(_) | JsonValue::BigInteger(_), JsonValue::Number(_) | JsonValue::BigInteger(_)) => compare_numbers(left, right) == Some(Ordering::Equal),
+        (left, right) => left == right,
+    }
+}
+
+/// Orders two numbers, comparing the digits of integers whenever one of them is a `BigInteger`. A number that isn't an
+/// integer can only be compared as an `f64`, but a `BigInteger` is too far from zero for that to change the outcome.
+fn compare_numbers(left: &JsonValue, right: &JsonValue) -> Option<Ordering> {
+    let digits = |value: &JsonValue| match value {
+        JsonValue::BigInteger(digits) => Some(digits.clone()),
+        JsonValue::Number(number) if number.is_finite() && number.fract() == 0.0 => Some(normalize_integer(format!("{:.0}", number).as_str())),
+        _ => None,
+    };
+    let number = |value: &JsonValue| match value {
+        JsonValue::BigInteger(digits) => digits.parse::<f64>().ok(),
+        JsonValue::Number(number) => Some(*number),
+        _ => None,
+    };
+
+    match (left, right) {
+        (JsonValue::Number(left), JsonValue::Number(right)) => left.partial_cmp(right),
+        (JsonValue::Number(_) | JsonValue::BigInteger(_), JsonValue::Number(_) | JsonValue::BigInteger(_)) => match (digits(left), digits(right)) {
+            (Some(left), Some(right)) => Some(compare_integers(left.as_str(), right.as_str())),
+            _ => number(left)?.partial_cmp(&number(right)?),
+        },
+        _ => None,
+    }
+}
+
+fn compare_integers(left: &str, right: &str) -> Ordering {
+    let magnitude = |digits: &str| (digits.len(), String::from(digits));
+
+    match (left.strip_prefix('-'), right.strip_prefix('-')) {
+        (Some(left), Some(right)) => magnitude(right).cmp(&magnitude(left)),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => magnitude(left).cmp(&magnitude(right)),
+    }
+}
+
+/// Drops an integer's leading zeros, along with the `-` of a negative zero.
+fn normalize_integer(raw: &str) -> String {
+    let (sign, digits) = match raw.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", raw.strip_prefix('+').unwrap_or(raw)),
+    };
+
+    match digits.trim_start_matches('0') {
+        "" => String::from("0"),
+        digits => format!("{}{}", sign, digits),
+    }
+}
+
+/// Reads a number, keeping integers with more digits than an `f64` can always hold exactly as a `BigInteger`.
+fn number_value(raw: &str) -> Option<JsonValue> {
+    let digits = raw.strip_prefix(['-', '+']).unwrap_or(raw);
+
+    if !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()) && digits.trim_start_matches('0').len() > 15 {
+        return Some(JsonValue::BigInteger(normalize_integer(raw)));
+    }
+
+    raw.parse::<f64>().ok().map(JsonValue::Number)
+}
+
+/// Builds whole values up out of their tokens, ignoring the whitespace and delimiters between them.
+pub struct JsonValueBuilder {
+    containers: Vec<(JsonValue, Option<String>)>,
+    name: Option<String>,
+}
+
+impl JsonValueBuilder {
+    pub fn new() -> JsonValueBuilder {
+        JsonValueBuilder { containers: Vec::new(), name: None }
+    }
+
+    /// Adds a token to the value being built, returning the value once it is complete.
+    pub fn push_token(&mut self, token: &JsonToken) -> Option<JsonValue> {
+        let value = match token {
+            JsonToken::PropertyName { raw: _, name } => {
+                self.name = Some(name.clone());
+                return None;
+            }
+            JsonToken::ObjectOpen(_) => {
+                self.containers.push((JsonValue::Object(Vec::new()), self.name.take()));
+                return None;
+            }
+            JsonToken::ArrayOpen(_) => {
+                self.containers.push((JsonValue::Array(Vec::new()), self.name.take()));
+                return None;
+            }
+            JsonToken::ObjectClose(_) | JsonToken::ArrayClose(_) => match self.containers.pop() {
+                Some((value, name)) => {
+                    self.name = name;
+                    value
+                }
+                None => return None,
+            },
+            JsonToken::StringValue { raw: _, value } => JsonValue::String(value.clone()),
+            JsonToken::IntegerValue { raw, value: _ } | JsonToken::BigIntegerValue(raw) => number_value(raw).unwrap_or(JsonValue::Null),
+            JsonToken::FloatValue { raw: _, value } => JsonValue::Number(*value),
+            JsonToken::BooleanValue { raw: _, value } => JsonValue::Boolean(*value),
+            JsonToken::NullValue(_) | JsonToken::UndefinedValue(_) => JsonValue::Null,
+            _ => return None,
+        };
+
+        let name = self.name.take().unwrap_or_default();
+
+        match self.containers.last_mut() {
+            Some((JsonValue::Object(members), _)) => members.push((name, value)),
+            Some((JsonValue::Array(items), _)) => items.push(value),
+            _ => return Some(value),
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(expression: &str) -> JsonFilterError {
+        match JsonFilter::parse(expression) {
+            Ok(_) => panic!("{} should not parse", expression),
+            Err(err) => err,
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(error("@.a ==").position, 6);
+        assert_eq!(error("(@.a == 1").expected, "')'");
+        assert_eq!(error("@.a == 'b").expected, "a closing '");
+        assert_eq!(error("$.a == 1").position, 0);
+        assert_eq!(error("1").expected, "a comparison such as '==' or '<'");
+        assert_eq!(error("@.a = 1").position, 4);
+    }
+
+    #[test]
+    fn test_non_singular_comparisons() {
+        let expected = "a path to a single value, with only member names and indices, to compare";
+
+        assert_eq!(
+            error("@.a[*] == 1"),
+            JsonFilterError {
+                position: 0,
+                expected: String::from(expected)
+            }
+        );
+        assert_eq!(
+            error("@..x == 1"),
+            JsonFilterError {
+                position: 0,
+                expected: String::from(expected)
+            }
+        );
+        assert_eq!(
+            error("1 < @.a[0:2]"),
+            JsonFilterError {
+                position: 4,
+                expected: String::from(expected)
+            }
+        );
+        assert_eq!(
+            error("@.a == 1 || @['b','c'] == 2"),
+            JsonFilterError {
+                position: 12,
+                expected: String::from(expected)
+            }
+        );
+        assert!(JsonFilter::parse("@.a[*]").is_ok());
+        assert!(JsonFilter::parse("@..x && @.a['b'][-1] == @[0].c").is_ok());
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(JsonFilter::parse("(@.a == 1)").unwrap().to_string(), "@.a == 1");
+        assert_eq!(JsonFilter::parse("(@.a) && (@.b)").unwrap().to_string(), "(@.a) && (@.b)");
+        assert_eq!(JsonFilter::parse("@.a == ')'").unwrap().to_string(), "@.a == ')'");
+    }
+
+    #[test]
+    fn test_comparisons() {
+        let number = |n: f64| Some(JsonValue::Number(n));
+        let string = |s: &str| Some(JsonValue::String(String::from(s)));
+
+        assert!(compare(number(1.0).as_ref(), JsonComparison::Less, number(2.0).as_ref()));
+        assert!(compare(string("a").as_ref(), JsonComparison::LessOrEqual, string("a").as_ref()));
+        assert!(!compare(number(1.0).as_ref(), JsonComparison::Less, string("2").as_ref()));
+        assert!(!compare(Some(&JsonValue::Boolean(false)), JsonComparison::Less, Some(&JsonValue::Boolean(true))));
+        assert!(compare(Some(&JsonValue::Boolean(true)), JsonComparison::GreaterOrEqual, Some(&JsonValue::Boolean(true))));
+        assert!(compare(None, JsonComparison::Equal, None));
+        assert!(compare(None, JsonComparison::NotEqual, Some(&JsonValue::Null)));
+        assert!(compare(None, JsonComparison::LessOrEqual, None));
+
+        let left = JsonValue::Object(vec![(String::from("a"), JsonValue::Number(1.0)), (String::from("b"), JsonValue::Null)]);
+        let right = JsonValue::Object(vec![(String::from("b"), JsonValue::Null), (String::from("a"), JsonValue::Number(1.0))]);
+
+        assert!(compare(Some(&left), JsonComparison::Equal, Some(&right)));
+
+        let big = |raw: &str| number_value(raw);
+
+        assert!(compare(
+            big("123456789012345678901234567890").as_ref(),
+            JsonComparison::Equal,
+            big("0123456789012345678901234567890").as_ref()
+        ));
+        assert!(compare(
+            big("123456789012345678901234567890").as_ref(),
+            JsonComparison::Less,
+            big("123456789012345678901234567891").as_ref()
+        ));
+        assert!(compare(
+            big("-123456789012345678901234567891").as_ref(),
+            JsonComparison::Less,
+            big("-123456789012345678901234567890").as_ref()
+        ));
+        assert!(compare(big("-99999999999999999999").as_ref(), JsonComparison::Less, big("1").as_ref()));
+        assert!(compare(big("1e30").as_ref(), JsonComparison::Equal, big("1000000000000000019884624838656").as_ref()));
+        assert!(compare(big("1e30").as_ref(), JsonComparison::Less, big("1000000000000000019884624838657").as_ref()));
+        assert!(compare(big("0.5").as_ref(), JsonComparison::Less, big("10000000000000000000").as_ref()));
+    }
+}
@@ -1,14 +1,17 @@
 use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
 
 use strum_macros::Display;
 
-#[derive(Display, PartialEq)]
+#[derive(Display, PartialEq, Clone)]
 pub enum JsonToken {
     PropertyName { raw: String, name: String },
     BooleanValue { raw: String, value: bool },
     StringValue { raw: String, value: String },
     IntegerValue { raw: String, value: isize },
     FloatValue { raw: String, value: f64 },
+    BigIntegerValue(String),
     NullValue(String),
     UndefinedValue(String),
     ObjectOpen(String),
@@ -22,17 +25,46 @@ pub enum JsonToken {
     KeyValueDelimiter(String),
 }
 
+impl JsonToken {
+    pub fn raw(&self) -> &str {
+        match self {
+            JsonToken::PropertyName { raw, name: _ } => raw,
+            JsonToken::BooleanValue { raw, value: _ } => raw,
+            JsonToken::StringValue { raw, value: _ } => raw,
+            JsonToken::IntegerValue { raw, value: _ } => raw,
+            JsonToken::FloatValue { raw, value: _ } => raw,
+            JsonToken::BigIntegerValue(raw) => raw,
+            JsonToken::NullValue(raw) => raw,
+            JsonToken::UndefinedValue(raw) => raw,
+            JsonToken::ObjectOpen(raw) => raw,
+            JsonToken::ObjectClose(raw) => raw,
+            JsonToken::ArrayOpen(raw) => raw,
+            JsonToken::ArrayClose(raw) => raw,
+            JsonToken::Whitespace(raw) => raw,
+            JsonToken::NewLine(raw) => raw,
+            JsonToken::ArrayItemDelimiter(raw) => raw,
+            JsonToken::PropertyDelimiter(raw) => raw,
+            JsonToken::KeyValueDelimiter(raw) => raw,
+        }
+    }
+}
+
 pub enum JsonPartialToken {
     Array,
     Object,
+    FirstPropertyName,
     PropertyName,
+    KeyValueDelimiter,
     PropertyValue,
+    FirstArrayValue,
     ArrayValue,
     BooleanValue { raw: String, value: bool },
     StringValue { raw: String, value: String },
+    StringEscape { raw: String, value: String, escape: String },
     NullValue { raw: String },
     UndefinedValue { raw: String },
     Root,
+    End,
     NumberValue(String),
     Whitespace(String),
 }
@@ -42,879 +74,593 @@ pub enum JsonStreamStatus {
     Token(JsonToken),
 }
 
+/// A place in the input. Lines and columns count from 1, with columns counted in characters, and the offset is in bytes
+/// from the start of the input.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct JsonPosition {
+    pub line: usize,
+    pub column: usize,
+    pub offset: usize,
+}
+
+impl JsonPosition {
+    fn new() -> JsonPosition {
+        JsonPosition { line: 1, column: 1, offset: 0 }
+    }
+
+    /// Moves the position on past a character, onto the start of the next line for a new line.
+    fn advance(&mut self, c: char) {
+        self.offset += c.len_utf8();
+
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+    }
+}
+
+/// Where a token is in the input, from its first character up to the position just past its last one.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct JsonSpan {
+    pub start: JsonPosition,
+    pub end: JsonPosition,
+}
+
+/// The first thing in the input that couldn't be lexed, along with the line it is on up to that point.
+#[derive(Clone, Debug, PartialEq)]
+pub struct JsonLexerError {
+    pub position: JsonPosition,
+    pub expected: String,
+    pub found: String,
+    pub snippet: String,
+}
+
+impl fmt::Display for JsonLexerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "expected {} but found {} at line {}, column {} (byte {})",
+            self.expected, self.found, self.position.line, self.position.column, self.position.offset
+        )?;
+        write_caret(f, &self.snippet, self.position.column - 1)
+    }
+}
+
+impl Error for JsonLexerError {}
+
+/// Writes out a line of input with a caret under the character `column` characters into it. Tabs are kept so the caret
+/// lines up with the line above it.
+pub fn write_caret(f: &mut fmt::Formatter<'_>, snippet: &str, column: usize) -> fmt::Result {
+    writeln!(f, "{}", snippet)?;
+
+    let indentation: String = snippet.chars().take(column).map(|c| if c == '\t' { '\t' } else { ' ' }).collect();
+
+    write!(f, "{}^", indentation)
+}
+
 pub struct JsonStreamLexer {
     tokens: VecDeque<JsonToken>,
     partial_tokens: Vec<JsonPartialToken>,
     current_line: String,
-    is_error: bool,
+    current_char: Option<char>,
+    position: JsonPosition,
+    token_position: JsonPosition,
+    error: Option<JsonLexerError>,
 }
 
 impl JsonStreamLexer {
     pub fn new() -> JsonStreamLexer {
-        let mut partial_tokens = Vec::new();
-        partial_tokens.push(JsonPartialToken::Root);
+        let partial_tokens = vec![JsonPartialToken::Root];
 
         JsonStreamLexer {
             tokens: VecDeque::new(),
             partial_tokens,
             current_line: String::new(),
-            is_error: false,
+            current_char: None,
+            position: JsonPosition::new(),
+            token_position: JsonPosition::new(),
+            error: None,
         }
     }
 
-    pub fn close(&mut self) {
+    /// Finishes off any tokens still in progress at the end of the input. An error is returned if the input couldn't be
+    /// lexed or ended partway through the document.
+    pub fn close(&mut self) -> Result<(), JsonLexerError> {
+        self.current_char = None;
+
         while let Some(partial_token) = self.partial_tokens.pop() {
             match partial_token {
-                JsonPartialToken::Array => {}
-                JsonPartialToken::Object => {}
-                JsonPartialToken::PropertyName => {}
-                JsonPartialToken::PropertyValue => {}
-                JsonPartialToken::ArrayValue => {}
-                JsonPartialToken::BooleanValue { raw, value } => self.tokens.push_back(JsonToken::BooleanValue { raw, value }),
-                JsonPartialToken::NullValue { raw } => self.tokens.push_back(JsonToken::NullValue(raw)),
-                JsonPartialToken::UndefinedValue { raw } => self.tokens.push_back(JsonToken::UndefinedValue(raw)),
-                JsonPartialToken::StringValue { raw, value } => self.tokens.push_back(JsonToken::StringValue { raw, value }),
-                JsonPartialToken::Root => {}
-                JsonPartialToken::NumberValue(raw_number) => {
-                    if raw_number.contains(".") {
-                        if let Ok(number) = raw_number.as_str().parse::<f64>() {
-                            self.tokens.push_back(JsonToken::FloatValue { raw: raw_number, value: number });
-                        }
-                    } else {
-                        if let Ok(number) = raw_number.as_str().parse::<isize>() {
-                            self.tokens.push_back(JsonToken::IntegerValue { raw: raw_number, value: number });
-                        }
-                    }
-                }
+                JsonPartialToken::Root | JsonPartialToken::End => {}
+                JsonPartialToken::NumberValue(raw_number) => self.push_number(raw_number),
                 JsonPartialToken::Whitespace(whitespace) => self.tokens.push_back(JsonToken::Whitespace(whitespace)),
+                _ => self.set_error(expected(&partial_token)),
             }
         }
+
+        match &self.error {
+            Some(error) => Err(error.clone()),
+            None => Ok(()),
+        }
     }
 
     pub fn pop_token(&mut self) -> JsonStreamStatus {
-        match self.tokens.pop_front() {
-            Some(status) => JsonStreamStatus::Token(status),
+        match self.pop_token_with_span() {
+            Some((token, _)) => JsonStreamStatus::Token(token),
             None => JsonStreamStatus::None,
         }
     }
 
-    pub fn push_char(&mut self, c: char) -> Result<(), &str> {
-        match c {
-            '\n' => {
-                if !self.is_error {
-                    self.current_line = String::new();
-                } else {
-                    return Err(&self.current_line);
-                }
-            }
-            _ => self.current_line.push(c),
+    /// Hands back the next token along with where it is in the input. Every character of the input is part of exactly
+    /// one token, whitespace and new lines included, so each token starts where the one before it ended.
+    pub fn pop_token_with_span(&mut self) -> Option<(JsonToken, JsonSpan)> {
+        let token = self.tokens.pop_front()?;
+        let start = self.token_position;
+
+        for c in token.raw().chars() {
+            self.token_position.advance(c);
         }
 
-        match c {
-            '{' => {
-                if let Some(partial_token) = self.partial_tokens.pop() {
-                    match partial_token {
-                        JsonPartialToken::Array => {
-                            self.tokens.push_back(JsonToken::ObjectOpen(String::from(c)));
-                            self.partial_tokens.push(JsonPartialToken::Array);
-                            self.partial_tokens.push(JsonPartialToken::Object);
-                        }
-                        JsonPartialToken::Object => self.is_error = true,
-                        JsonPartialToken::PropertyName => self.is_error = true,
-                        JsonPartialToken::PropertyValue => {
-                            self.tokens.push_back(JsonToken::ObjectOpen(String::from(c)));
-                            self.partial_tokens.push(JsonPartialToken::PropertyValue);
-                            self.partial_tokens.push(JsonPartialToken::Object);
-                        }
-                        JsonPartialToken::ArrayValue => {
-                            self.tokens.push_back(JsonToken::ObjectOpen(String::from(c)));
-                            self.partial_tokens.push(JsonPartialToken::ArrayValue);
-                            self.partial_tokens.push(JsonPartialToken::Object);
-                        }
-                        JsonPartialToken::BooleanValue { raw: _, value: _ } => self.is_error = true,
-                        JsonPartialToken::NullValue { raw: _ } => self.is_error = true,
-                        JsonPartialToken::UndefinedValue { raw: _ } => self.is_error = true,
-                        JsonPartialToken::StringValue { mut raw, mut value } => {
-                            raw.push(c);
-                            value.push(c);
-                            self.partial_tokens.push(JsonPartialToken::StringValue { raw, value });
-                        }
-                        JsonPartialToken::Root => {
-                            self.tokens.push_back(JsonToken::ObjectOpen(String::from(c)));
-                            self.partial_tokens.push(JsonPartialToken::Root);
-                            self.partial_tokens.push(JsonPartialToken::Object);
-                            self.partial_tokens.push(JsonPartialToken::PropertyName);
-                        }
-                        JsonPartialToken::NumberValue(_) => self.is_error = true,
-                        JsonPartialToken::Whitespace(whitespace) => {
-                            self.tokens.push_back(JsonToken::Whitespace(whitespace));
-                            self.tokens.push_back(JsonToken::ObjectOpen(String::from(c)));
-                            self.partial_tokens.push(JsonPartialToken::Object);
-                            self.partial_tokens.push(JsonPartialToken::PropertyName);
-                        }
+        Some((token, JsonSpan { start, end: self.token_position }))
+    }
+
+    pub fn push_char(&mut self, c: char) -> Result<(), JsonLexerError> {
+        if let Some(error) = &self.error {
+            return Err(error.clone());
+        }
+
+        self.current_char = Some(c);
+
+        if c != '\n' {
+            self.current_line.push(c);
+        }
+
+        // Tokens that span several characters are finished here. Once one ends, the character that ended it is handled
+        // by the container it sits in.
+        if let Some(partial_token) = self.partial_tokens.pop() {
+            match partial_token {
+                JsonPartialToken::StringValue { raw, value } => self.push_string_char(raw, value, c),
+                JsonPartialToken::StringEscape { raw, value, escape } => self.push_escape_char(raw, value, escape, c),
+                JsonPartialToken::BooleanValue { raw, value } => self.push_keyword_char(JsonPartialToken::BooleanValue { raw, value }, c),
+                JsonPartialToken::NullValue { raw } => self.push_keyword_char(JsonPartialToken::NullValue { raw }, c),
+                JsonPartialToken::UndefinedValue { raw } => self.push_keyword_char(JsonPartialToken::UndefinedValue { raw }, c),
+                JsonPartialToken::NumberValue(mut raw_number) => match c {
+                    '0'..='9' | '.' | 'e' | 'E' | '+' | '-' => {
+                        raw_number.push(c);
+                        self.partial_tokens.push(JsonPartialToken::NumberValue(raw_number));
                     }
-                } else {
-                    self.is_error = true;
-                }
-            }
-            '}' => {
-                if let Some(partial_token) = self.partial_tokens.pop() {
-                    match partial_token {
-                        JsonPartialToken::Array => self.is_error = true,
-                        JsonPartialToken::Object => self.tokens.push_back(JsonToken::ObjectClose(String::from(c))),
-                        JsonPartialToken::PropertyName => self.is_error = true,
-                        JsonPartialToken::PropertyValue => self.tokens.push_back(JsonToken::ObjectClose(String::from(c))),
-                        JsonPartialToken::ArrayValue => self.is_error = true,
-                        JsonPartialToken::BooleanValue { raw: _, value: _ } => self.is_error = true,
-                        JsonPartialToken::NullValue { raw: _ } => self.is_error = true,
-                        JsonPartialToken::UndefinedValue { raw: _ } => self.is_error = true,
-                        JsonPartialToken::StringValue { mut raw, mut value } => {
-                            raw.push(c);
-                            value.push(c);
-                            self.partial_tokens.push(JsonPartialToken::StringValue { raw, value });
-                        }
-                        JsonPartialToken::Root => self.is_error = true,
-                        JsonPartialToken::NumberValue(raw_number) => {
-                            if raw_number.contains(".") {
-                                if let Ok(number) = raw_number.as_str().parse::<f64>() {
-                                    self.tokens.push_back(JsonToken::FloatValue { raw: raw_number, value: number });
-                                }
-                            } else {
-                                if let Ok(number) = raw_number.as_str().parse::<isize>() {
-                                    self.tokens.push_back(JsonToken::IntegerValue { raw: raw_number, value: number });
-                                }
-                            }
-
-                            self.tokens.push_back(JsonToken::ArrayClose(String::from(c)));
-
-                            if let Some(partial_token) = self.partial_tokens.pop() {
-                                match partial_token {
-                                    JsonPartialToken::Object => {}
-                                    _ => self.is_error = true,
-                                }
-                            }
-                        }
-                        JsonPartialToken::Whitespace(whitespace) => {
-                            self.tokens.push_back(JsonToken::Whitespace(whitespace));
-                            self.tokens.push_back(JsonToken::ObjectClose(String::from(c)));
-
-                            if let Some(partial_token) = self.partial_tokens.pop() {
-                                match partial_token {
-                                    JsonPartialToken::Object => {}
-                                    JsonPartialToken::PropertyValue => {
-                                        if let Some(partial_token) = self.partial_tokens.pop() {
-                                            match partial_token {
-                                                JsonPartialToken::Object => {}
-                                                _ => self.is_error = true,
-                                            }
-                                        }
-                                    }
-                                    _ => self.is_error = true,
-                                }
-                            }
-                        }
+                    _ => {
+                        self.push_number(raw_number);
+                        self.push_structural_char(c);
                     }
-                } else {
-                    self.is_error = true;
-                }
-            }
-            '[' => {
-                if let Some(partial_token) = self.partial_tokens.pop() {
-                    match partial_token {
-                        JsonPartialToken::Array => {
-                            self.tokens.push_back(JsonToken::ArrayOpen(String::from(c)));
-                            self.partial_tokens.push(JsonPartialToken::Array);
-                            self.partial_tokens.push(JsonPartialToken::Array);
-                        }
-                        JsonPartialToken::Object => self.is_error = true,
-                        JsonPartialToken::PropertyName => self.is_error = true,
-                        JsonPartialToken::PropertyValue => {
-                            self.tokens.push_back(JsonToken::ArrayOpen(String::from(c)));
-                            self.partial_tokens.push(JsonPartialToken::PropertyValue);
-                            self.partial_tokens.push(JsonPartialToken::Array);
-                        }
-                        JsonPartialToken::ArrayValue => {
-                            self.tokens.push_back(JsonToken::ArrayOpen(String::from(c)));
-                            self.partial_tokens.push(JsonPartialToken::ArrayValue);
-                            self.partial_tokens.push(JsonPartialToken::Array);
-                        }
-                        JsonPartialToken::BooleanValue { raw: _, value: _ } => self.is_error = true,
-                        JsonPartialToken::NullValue { raw: _ } => self.is_error = true,
-                        JsonPartialToken::UndefinedValue { raw: _ } => self.is_error = true,
-                        JsonPartialToken::StringValue { mut raw, mut value } => {
-                            raw.push(c);
-                            value.push(c);
-                            self.partial_tokens.push(JsonPartialToken::StringValue { raw, value });
-                        }
-                        JsonPartialToken::Root => {
-                            self.tokens.push_back(JsonToken::ArrayOpen(String::from(c)));
-                            self.partial_tokens.push(JsonPartialToken::Root);
-                            self.partial_tokens.push(JsonPartialToken::Array);
-                            self.partial_tokens.push(JsonPartialToken::ArrayValue);
-                        }
-                        JsonPartialToken::NumberValue(_) => self.is_error = true,
-                        JsonPartialToken::Whitespace(whitespace) => {
-                            self.tokens.push_back(JsonToken::Whitespace(whitespace));
-
-                            if let Some(partial_token) = self.partial_tokens.pop() {
-                                match partial_token {
-                                    JsonPartialToken::Array => {
-                                        self.tokens.push_back(JsonToken::ArrayOpen(String::from(c)));
-                                        self.partial_tokens.push(JsonPartialToken::Array);
-                                        self.partial_tokens.push(JsonPartialToken::Array);
-                                    }
-                                    JsonPartialToken::Object => self.is_error = true,
-                                    JsonPartialToken::PropertyName => self.is_error = true,
-                                    JsonPartialToken::PropertyValue => {
-                                        self.tokens.push_back(JsonToken::ArrayOpen(String::from(c)));
-                                        self.partial_tokens.push(JsonPartialToken::PropertyValue);
-                                        self.partial_tokens.push(JsonPartialToken::Array);
-                                    }
-                                    JsonPartialToken::ArrayValue => {
-                                        self.tokens.push_back(JsonToken::ArrayOpen(String::from(c)));
-                                        self.partial_tokens.push(JsonPartialToken::ArrayValue);
-                                        self.partial_tokens.push(JsonPartialToken::Array);
-                                    }
-                                    JsonPartialToken::BooleanValue { raw: _, value: _ } => self.is_error = true,
-                                    JsonPartialToken::NullValue { raw: _ } => self.is_error = true,
-                                    JsonPartialToken::UndefinedValue { raw: _ } => self.is_error = true,
-                                    JsonPartialToken::StringValue { raw: _, value: _ } => self.is_error = true,
-                                    JsonPartialToken::Root => {
-                                        self.tokens.push_back(JsonToken::ArrayOpen(String::from(c)));
-                                        self.partial_tokens.push(JsonPartialToken::Root);
-                                        self.partial_tokens.push(JsonPartialToken::Array);
-                                    }
-                                    JsonPartialToken::NumberValue(_) => self.is_error = true,
-                                    JsonPartialToken::Whitespace(_) => self.is_error = true,
-                                }
-                            } else {
-                                self.is_error = true;
-                            }
-                        }
+                },
+                JsonPartialToken::Whitespace(mut whitespace) => match c {
+                    ' ' | '\t' | '\r' => {
+                        whitespace.push(c);
+                        self.partial_tokens.push(JsonPartialToken::Whitespace(whitespace));
                     }
-                } else {
-                    self.is_error = true;
-                }
-            }
-            ']' => {
-                if let Some(partial_token) = self.partial_tokens.pop() {
-                    match partial_token {
-                        JsonPartialToken::Array => self.tokens.push_back(JsonToken::ArrayClose(String::from(c))),
-                        JsonPartialToken::Object => self.is_error = true,
-                        JsonPartialToken::PropertyName => self.is_error = true,
-                        JsonPartialToken::PropertyValue => self.is_error = true,
-                        JsonPartialToken::ArrayValue => self.tokens.push_back(JsonToken::ArrayClose(String::from(c))),
-                        JsonPartialToken::BooleanValue { raw: _, value: _ } => self.is_error = true,
-                        JsonPartialToken::NullValue { raw: _ } => self.is_error = true,
-                        JsonPartialToken::UndefinedValue { raw: _ } => self.is_error = true,
-                        JsonPartialToken::StringValue { mut raw, mut value } => {
-                            raw.push(c);
-                            value.push(c);
-                            self.partial_tokens.push(JsonPartialToken::StringValue { raw, value });
-                        }
-                        JsonPartialToken::Root => self.is_error = true,
-                        JsonPartialToken::NumberValue(raw_number) => {
-                            if raw_number.contains(".") {
-                                if let Ok(number) = raw_number.as_str().parse::<f64>() {
-                                    self.tokens.push_back(JsonToken::FloatValue { raw: raw_number, value: number });
-                                }
-                            } else {
-                                if let Ok(number) = raw_number.as_str().parse::<isize>() {
-                                    self.tokens.push_back(JsonToken::IntegerValue { raw: raw_number, value: number });
-                                }
-                            }
-
-                            self.tokens.push_back(JsonToken::ArrayClose(String::from(c)));
-
-                            if let Some(partial_token) = self.partial_tokens.pop() {
-                                match partial_token {
-                                    JsonPartialToken::Array => {}
-                                    _ => self.is_error = true,
-                                }
-                            }
-                        }
-                        JsonPartialToken::Whitespace(whitespace) => {
+                    '\n' if whitespace.ends_with('\r') => {
+                        whitespace.pop();
+
+                        if !whitespace.is_empty() {
                             self.tokens.push_back(JsonToken::Whitespace(whitespace));
-                            self.tokens.push_back(JsonToken::ArrayClose(String::from(c)));
-
-                            if let Some(partial_token) = self.partial_tokens.pop() {
-                                match partial_token {
-                                    JsonPartialToken::Array => {}
-                                    _ => self.is_error = true,
-                                }
-                            }
                         }
+
+                        self.tokens.push_back(JsonToken::NewLine(String::from("\r\n")));
                     }
-                } else {
-                    self.is_error = true;
+                    _ => {
+                        self.tokens.push_back(JsonToken::Whitespace(whitespace));
+                        self.push_structural_char(c);
+                    }
+                },
+                _ => {
+                    self.partial_tokens.push(partial_token);
+                    self.push_structural_char(c);
                 }
             }
-            '"' => {
-                if let Some(partial_token) = self.partial_tokens.pop() {
-                    match partial_token {
-                        JsonPartialToken::Array => {
-                            self.partial_tokens.push(JsonPartialToken::Array);
-                            self.partial_tokens.push(JsonPartialToken::StringValue {
-                                raw: String::from(c),
-                                value: String::new(),
-                            });
-                        }
-                        JsonPartialToken::Object => self.is_error = true,
-                        JsonPartialToken::PropertyName => {
-                            self.partial_tokens.push(JsonPartialToken::PropertyName);
-                            self.partial_tokens.push(JsonPartialToken::StringValue {
-                                raw: String::from(c),
-                                value: String::new(),
-                            });
-                        }
-                        JsonPartialToken::PropertyValue => {
-                            self.partial_tokens.push(JsonPartialToken::PropertyValue);
-                            self.partial_tokens.push(JsonPartialToken::StringValue {
-                                raw: String::from(c),
-                                value: String::new(),
-                            });
-                        }
-                        JsonPartialToken::ArrayValue => {
-                            self.partial_tokens.push(JsonPartialToken::ArrayValue);
-                            self.partial_tokens.push(JsonPartialToken::StringValue {
-                                raw: String::from(c),
-                                value: String::new(),
-                            });
-                        }
-                        JsonPartialToken::BooleanValue { raw: _, value: _ } => self.is_error = true,
-                        JsonPartialToken::NullValue { raw: _ } => self.is_error = true,
-                        JsonPartialToken::UndefinedValue { raw: _ } => self.is_error = true,
-                        JsonPartialToken::StringValue { mut raw, value } => {
-                            raw.push(c);
-                            if let Some(partial_token) = self.partial_tokens.pop() {
-                                match partial_token {
-                                    JsonPartialToken::Array => {
-                                        self.tokens.push_back(JsonToken::StringValue { raw, value });
-                                        self.partial_tokens.push(JsonPartialToken::Array);
-                                    }
-                                    JsonPartialToken::Object => {
-                                        self.tokens.push_back(JsonToken::PropertyName { raw, name: value });
-                                        self.partial_tokens.push(JsonPartialToken::Object);
-                                        self.partial_tokens.push(JsonPartialToken::PropertyValue);
-                                    }
-                                    JsonPartialToken::PropertyName => {
-                                        self.tokens.push_back(JsonToken::PropertyName { raw, name: value });
-                                        self.partial_tokens.push(JsonPartialToken::PropertyValue);
-                                    }
-                                    JsonPartialToken::PropertyValue => {
-                                        self.tokens.push_back(JsonToken::StringValue { raw, value });
-                                    }
-                                    JsonPartialToken::ArrayValue => {
-                                        self.tokens.push_back(JsonToken::StringValue { raw, value });
-                                    }
-                                    JsonPartialToken::BooleanValue { raw: _, value: _ } => self.is_error = true,
-                                    JsonPartialToken::NullValue { raw: _ } => self.is_error = true,
-                                    JsonPartialToken::UndefinedValue { raw: _ } => self.is_error = true,
-                                    JsonPartialToken::StringValue { raw: _, value: _ } => self.is_error = true,
-                                    JsonPartialToken::Root => self.is_error = true,
-                                    JsonPartialToken::NumberValue(_) => self.is_error = true,
-                                    JsonPartialToken::Whitespace(_) => self.is_error = true,
-                                }
-                            } else {
-                                self.is_error = true;
-                            }
-                        }
-                        JsonPartialToken::Root => self.is_error = true,
-                        JsonPartialToken::NumberValue(_) => self.is_error = true,
-                        JsonPartialToken::Whitespace(whitespace) => {
-                            self.tokens.push_back(JsonToken::Whitespace(whitespace));
+        } else {
+            self.set_error("the end of the input");
+        }
 
-                            if let Some(partial_token) = self.partial_tokens.pop() {
-                                match partial_token {
-                                    JsonPartialToken::Array => {
-                                        self.partial_tokens.push(JsonPartialToken::Array);
-                                        self.partial_tokens.push(JsonPartialToken::StringValue {
-                                            raw: String::from(c),
-                                            value: String::new(),
-                                        });
-                                    }
-                                    JsonPartialToken::Object => {
-                                        self.partial_tokens.push(JsonPartialToken::Object);
-                                        self.partial_tokens.push(JsonPartialToken::StringValue {
-                                            raw: String::from(c),
-                                            value: String::new(),
-                                        });
-                                    }
-                                    JsonPartialToken::PropertyName => {
-                                        self.partial_tokens.push(JsonPartialToken::PropertyName);
-                                        self.partial_tokens.push(JsonPartialToken::StringValue {
-                                            raw: String::from(c),
-                                            value: String::new(),
-                                        });
-                                    }
-                                    JsonPartialToken::PropertyValue => {
-                                        self.partial_tokens.push(JsonPartialToken::PropertyValue);
-                                        self.partial_tokens.push(JsonPartialToken::StringValue {
-                                            raw: String::from(c),
-                                            value: String::new(),
-                                        });
-                                    }
-                                    JsonPartialToken::ArrayValue => {
-                                        self.partial_tokens.push(JsonPartialToken::ArrayValue);
-                                        self.partial_tokens.push(JsonPartialToken::StringValue {
-                                            raw: String::from(c),
-                                            value: String::new(),
-                                        });
-                                    }
-                                    JsonPartialToken::BooleanValue { raw: _, value: _ } => self.is_error = true,
-                                    JsonPartialToken::NullValue { raw: _ } => self.is_error = true,
-                                    JsonPartialToken::UndefinedValue { raw: _ } => self.is_error = true,
-                                    JsonPartialToken::StringValue { raw: _, value: _ } => self.is_error = true,
-                                    JsonPartialToken::Root => self.is_error = true,
-                                    JsonPartialToken::NumberValue(_) => self.is_error = true,
-                                    JsonPartialToken::Whitespace(_) => self.is_error = true,
-                                }
-                            } else {
-                                self.is_error = true;
-                            }
-                        }
-                    }
-                } else {
-                    self.is_error = true;
+        if let Some(error) = &self.error {
+            return Err(error.clone());
+        }
+
+        self.position.advance(c);
+
+        if c == '\n' {
+            self.current_line.clear();
+        }
+
+        Ok(())
+    }
+
+    /// Handles a character that is outside of any string, number or keyword. The partial token on the top of the stack
+    /// is always the container the character appears in.
+    ///
+    /// `FirstPropertyName`, `PropertyName`, `FirstArrayValue` and `ArrayValue` mean a member or item is expected next, the
+    /// first ones straight after the container opened so it can be closed again, `KeyValueDelimiter` means a member's name
+    /// has been read and `PropertyValue` that its `:` has too, and `Object` and `Array` mean a member or item has just
+    /// finished. A value consumes the `PropertyValue` or `ArrayValue` it was expected by, so when it finishes the container
+    /// it belongs to is back on the top of the stack.
+    fn push_structural_char(&mut self, c: char) {
+        let partial_token = match self.partial_tokens.pop() {
+            Some(partial_token) => partial_token,
+            None => return self.set_error("the end of the input"),
+        };
+
+        match c {
+            '{' => {
+                if self.start_value(partial_token) {
+                    self.tokens.push_back(JsonToken::ObjectOpen(String::from(c)));
+                    self.partial_tokens.push(JsonPartialToken::Object);
+                    self.partial_tokens.push(JsonPartialToken::FirstPropertyName);
                 }
             }
-            ' ' | '\t' => {
-                if let Some(partial_token) = self.partial_tokens.pop() {
-                    match partial_token {
-                        JsonPartialToken::Array => {
-                            self.partial_tokens.push(JsonPartialToken::Array);
-                            self.partial_tokens.push(JsonPartialToken::Whitespace(String::from(c)));
-                        }
-                        JsonPartialToken::Object => {
-                            self.partial_tokens.push(JsonPartialToken::Object);
-                            self.partial_tokens.push(JsonPartialToken::Whitespace(String::from(c)));
-                        }
-                        JsonPartialToken::PropertyName => {
-                            self.partial_tokens.push(JsonPartialToken::PropertyName);
-                            self.partial_tokens.push(JsonPartialToken::Whitespace(String::from(c)));
-                        }
-                        JsonPartialToken::PropertyValue => {
-                            self.partial_tokens.push(JsonPartialToken::PropertyValue);
-                            self.partial_tokens.push(JsonPartialToken::Whitespace(String::from(c)));
-                        }
-                        JsonPartialToken::ArrayValue => {
-                            self.partial_tokens.push(JsonPartialToken::ArrayValue);
-                            self.partial_tokens.push(JsonPartialToken::Whitespace(String::from(c)));
-                        }
-                        JsonPartialToken::BooleanValue { raw: _, value: _ } => self.is_error = true,
-                        JsonPartialToken::NullValue { raw: _ } => self.is_error = true,
-                        JsonPartialToken::UndefinedValue { raw: _ } => self.is_error = true,
-                        JsonPartialToken::StringValue { mut raw, mut value } => {
-                            raw.push(c);
-                            value.push(c);
-                            self.partial_tokens.push(JsonPartialToken::StringValue { raw, value });
-                        }
-                        JsonPartialToken::Root => {
-                            self.partial_tokens.push(JsonPartialToken::Root);
-                            self.partial_tokens.push(JsonPartialToken::Whitespace(String::from(c)));
-                        }
-                        JsonPartialToken::NumberValue(raw_number) => {
-                            if raw_number.contains(".") {
-                                if let Ok(number) = raw_number.as_str().parse::<f64>() {
-                                    self.tokens.push_back(JsonToken::FloatValue { raw: raw_number, value: number });
-                                }
-                            } else {
-                                if let Ok(number) = raw_number.as_str().parse::<isize>() {
-                                    self.tokens.push_back(JsonToken::IntegerValue { raw: raw_number, value: number });
-                                }
-                            }
-
-                            if let Some(partial_token) = self.partial_tokens.pop() {
-                                match partial_token {
-                                    JsonPartialToken::Array => {
-                                        self.partial_tokens.push(JsonPartialToken::Array);
-                                        self.partial_tokens.push(JsonPartialToken::Whitespace(String::from(c)));
-                                    }
-                                    JsonPartialToken::Object => {
-                                        self.partial_tokens.push(JsonPartialToken::Object);
-                                        self.partial_tokens.push(JsonPartialToken::Whitespace(String::from(c)));
-                                    }
-                                    JsonPartialToken::PropertyName => self.is_error = true,
-                                    JsonPartialToken::PropertyValue => {
-                                        self.partial_tokens.push(JsonPartialToken::PropertyValue);
-                                        self.partial_tokens.push(JsonPartialToken::Whitespace(String::from(c)));
-                                    }
-                                    JsonPartialToken::ArrayValue => {
-                                        self.partial_tokens.push(JsonPartialToken::ArrayValue);
-                                        self.partial_tokens.push(JsonPartialToken::Whitespace(String::from(c)));
-                                    }
-                                    JsonPartialToken::BooleanValue { raw: _, value: _ } => self.is_error = true,
-                                    JsonPartialToken::NullValue { raw: _ } => self.is_error = true,
-                                    JsonPartialToken::UndefinedValue { raw: _ } => self.is_error = true,
-                                    JsonPartialToken::StringValue { raw: _, value: _ } => self.is_error = true,
-                                    JsonPartialToken::Root => self.is_error = true,
-                                    JsonPartialToken::NumberValue(_) => self.is_error = true,
-                                    JsonPartialToken::Whitespace(_) => self.is_error = true,
-                                }
-                            } else {
-                                self.is_error = true;
-                            }
-                        }
-                        JsonPartialToken::Whitespace(mut whitespace) => {
-                            whitespace.push(c);
-                            self.partial_tokens.push(JsonPartialToken::Whitespace(whitespace));
-                        }
+            '}' => match partial_token {
+                JsonPartialToken::Object => self.tokens.push_back(JsonToken::ObjectClose(String::from(c))),
+                JsonPartialToken::FirstPropertyName => {
+                    if let Some(JsonPartialToken::Object) = self.partial_tokens.pop() {
+                        self.tokens.push_back(JsonToken::ObjectClose(String::from(c)));
+                    } else {
+                        self.set_error(expected(&partial_token));
                     }
-                } else {
-                    self.is_error = true;
+                }
+                _ => self.push_error(partial_token),
+            },
+            '[' => {
+                if self.start_value(partial_token) {
+                    self.tokens.push_back(JsonToken::ArrayOpen(String::from(c)));
+                    self.partial_tokens.push(JsonPartialToken::Array);
+                    self.partial_tokens.push(JsonPartialToken::FirstArrayValue);
                 }
             }
-            ':' => {
-                if let Some(partial_token) = self.partial_tokens.pop() {
-                    match partial_token {
-                        JsonPartialToken::Array => self.is_error = true,
-                        JsonPartialToken::Object => self.is_error = true,
-                        JsonPartialToken::PropertyName => self.is_error = true,
-                        JsonPartialToken::PropertyValue => {
-                            self.tokens.push_back(JsonToken::KeyValueDelimiter(String::from(c)));
-                            self.partial_tokens.push(JsonPartialToken::PropertyValue);
-                        }
-                        JsonPartialToken::ArrayValue => self.is_error = true,
-                        JsonPartialToken::BooleanValue { raw: _, value: _ } => self.is_error = true,
-                        JsonPartialToken::NullValue { raw: _ } => self.is_error = true,
-                        JsonPartialToken::UndefinedValue { raw: _ } => self.is_error = true,
-                        JsonPartialToken::StringValue { mut raw, mut value } => {
-                            raw.push(c);
-                            value.push(c);
-                            self.partial_tokens.push(JsonPartialToken::StringValue { raw, value });
-                        }
-                        JsonPartialToken::Root => self.is_error = true,
-                        JsonPartialToken::NumberValue(_) => self.is_error = true,
-                        JsonPartialToken::Whitespace(whitespace) => {
-                            if let Some(partial_token) = self.partial_tokens.pop() {
-                                match partial_token {
-                                    JsonPartialToken::Array => self.is_error = true,
-                                    JsonPartialToken::Object => self.is_error = true,
-                                    JsonPartialToken::PropertyName => self.is_error = true,
-                                    JsonPartialToken::PropertyValue => {
-                                        self.tokens.push_back(JsonToken::Whitespace(whitespace));
-                                        self.tokens.push_back(JsonToken::KeyValueDelimiter(String::from(c)));
-                                        self.partial_tokens.push(JsonPartialToken::PropertyValue);
-                                    }
-                                    JsonPartialToken::ArrayValue => self.is_error = true,
-                                    JsonPartialToken::BooleanValue { raw: _, value: _ } => self.is_error = true,
-                                    JsonPartialToken::NullValue { raw: _ } => self.is_error = true,
-                                    JsonPartialToken::UndefinedValue { raw: _ } => self.is_error = true,
-                                    JsonPartialToken::StringValue { raw: _, value: _ } => self.is_error = true,
-                                    JsonPartialToken::Root => self.is_error = true,
-                                    JsonPartialToken::NumberValue(_) => self.is_error = true,
-                                    JsonPartialToken::Whitespace(_) => self.is_error = true,
-                                }
-                            } else {
-                                self.is_error = true;
-                            }
-                        }
+            ']' => match partial_token {
+                JsonPartialToken::Array => self.tokens.push_back(JsonToken::ArrayClose(String::from(c))),
+                JsonPartialToken::FirstArrayValue => {
+                    if let Some(JsonPartialToken::Array) = self.partial_tokens.pop() {
+                        self.tokens.push_back(JsonToken::ArrayClose(String::from(c)));
+                    } else {
+                        self.set_error(expected(&partial_token));
                     }
-                } else {
-                    self.is_error = true;
                 }
-            }
-            ',' => {
-                if let Some(partial_token) = self.partial_tokens.pop() {
-                    match partial_token {
-                        JsonPartialToken::Array => {
-                            self.tokens.push_back(JsonToken::ArrayItemDelimiter(String::from(c)));
-                            self.partial_tokens.push(JsonPartialToken::Array);
-                        }
-                        JsonPartialToken::Object => {
-                            self.tokens.push_back(JsonToken::PropertyDelimiter(String::from(c)));
-                            self.partial_tokens.push(JsonPartialToken::Object);
-                            self.partial_tokens.push(JsonPartialToken::PropertyName);
-                        }
-                        JsonPartialToken::PropertyName => panic!("malformed property"),
-                        JsonPartialToken::PropertyValue => self.tokens.push_back(JsonToken::PropertyDelimiter(String::from(c))),
-                        JsonPartialToken::ArrayValue => self.tokens.push_back(JsonToken::ArrayItemDelimiter(String::from(c))),
-                        JsonPartialToken::BooleanValue { raw: _, value: _ } => self.is_error = true,
-                        JsonPartialToken::NullValue { raw: _ } => self.is_error = true,
-                        JsonPartialToken::UndefinedValue { raw: _ } => self.is_error = true,
-                        JsonPartialToken::StringValue { raw: _, value: _ } => panic!("malformed string"),
-                        JsonPartialToken::Root => self.is_error = true,
-                        JsonPartialToken::NumberValue(raw_number) => {
-                            if raw_number.contains(".") {
-                                if let Ok(number) = raw_number.as_str().parse::<f64>() {
-                                    self.tokens.push_back(JsonToken::FloatValue { raw: raw_number, value: number });
-                                } else {
-                                    panic!("malformed number");
-                                }
-                            } else {
-                                if let Ok(number) = raw_number.as_str().parse::<isize>() {
-                                    self.tokens.push_back(JsonToken::IntegerValue { raw: raw_number, value: number });
-                                } else {
-                                    panic!("malformed number");
-                                }
-                            }
-
-                            if let Some(partial_token) = self.partial_tokens.pop() {
-                                match partial_token {
-                                    JsonPartialToken::Array => {
-                                        self.tokens.push_back(JsonToken::ArrayItemDelimiter(String::from(c)));
-                                        self.partial_tokens.push(JsonPartialToken::Array);
-                                    }
-                                    JsonPartialToken::Object => {
-                                        self.tokens.push_back(JsonToken::PropertyDelimiter(String::from(c)));
-                                        self.partial_tokens.push(JsonPartialToken::Object);
-                                    }
-                                    JsonPartialToken::PropertyName => {
-                                        self.tokens.push_back(JsonToken::PropertyDelimiter(String::from(c)));
-                                        self.partial_tokens.push(JsonPartialToken::PropertyName);
-                                    }
-                                    JsonPartialToken::PropertyValue => {
-                                        self.tokens.push_back(JsonToken::PropertyDelimiter(String::from(c)));
-                                        self.partial_tokens.push(JsonPartialToken::PropertyValue);
-                                    }
-                                    JsonPartialToken::ArrayValue => {
-                                        self.tokens.push_back(JsonToken::ArrayItemDelimiter(String::from(c)));
-                                        self.partial_tokens.push(JsonPartialToken::ArrayValue);
-                                    }
-                                    JsonPartialToken::BooleanValue { raw: _, value: _ } => self.is_error = true,
-                                    JsonPartialToken::NullValue { raw: _ } => self.is_error = true,
-                                    JsonPartialToken::UndefinedValue { raw: _ } => self.is_error = true,
-                                    JsonPartialToken::StringValue { raw: _, value: _ } => self.is_error = true,
-                                    JsonPartialToken::Root => self.is_error = true,
-                                    JsonPartialToken::NumberValue(_) => self.is_error = true,
-                                    JsonPartialToken::Whitespace(_) => self.is_error = true,
-                                }
-                            } else {
-                                self.is_error = true;
-                            }
-                        }
-                        JsonPartialToken::Whitespace(_) => self.is_error = true,
+                _ => self.push_error(partial_token),
+            },
+            '"' => match partial_token {
+                JsonPartialToken::FirstPropertyName | JsonPartialToken::PropertyName => {
+                    self.partial_tokens.push(JsonPartialToken::PropertyName);
+                    self.partial_tokens.push(JsonPartialToken::StringValue {
+                        raw: String::from(c),
+                        value: String::new(),
+                    });
+                }
+                _ => {
+                    if self.start_value(partial_token) {
+                        self.partial_tokens.push(JsonPartialToken::StringValue {
+                            raw: String::from(c),
+                            value: String::new(),
+                        });
                     }
-                } else {
-                    self.is_error = true;
                 }
+            },
+            ':' => match partial_token {
+                JsonPartialToken::KeyValueDelimiter => {
+                    self.tokens.push_back(JsonToken::KeyValueDelimiter(String::from(c)));
+                    self.partial_tokens.push(JsonPartialToken::PropertyValue);
+                }
+                _ => self.push_error(partial_token),
+            },
+            ',' => match partial_token {
+                JsonPartialToken::Object => {
+                    self.tokens.push_back(JsonToken::PropertyDelimiter(String::from(c)));
+                    self.partial_tokens.push(JsonPartialToken::Object);
+                    self.partial_tokens.push(JsonPartialToken::PropertyName);
+                }
+                JsonPartialToken::Array => {
+                    self.tokens.push_back(JsonToken::ArrayItemDelimiter(String::from(c)));
+                    self.partial_tokens.push(JsonPartialToken::Array);
+                    self.partial_tokens.push(JsonPartialToken::ArrayValue);
+                }
+                _ => self.push_error(partial_token),
+            },
+            ' ' | '\t' | '\r' => {
+                self.partial_tokens.push(partial_token);
+                self.partial_tokens.push(JsonPartialToken::Whitespace(String::from(c)));
             }
             '\n' => {
-                if let Some(partial_token) = self.partial_tokens.pop() {
-                    match partial_token {
-                        JsonPartialToken::Array => self.partial_tokens.push(JsonPartialToken::Array),
-                        JsonPartialToken::Object => self.partial_tokens.push(JsonPartialToken::Object),
-                        JsonPartialToken::PropertyName => self.partial_tokens.push(JsonPartialToken::PropertyName),
-                        JsonPartialToken::PropertyValue => self.partial_tokens.push(JsonPartialToken::PropertyValue),
-                        JsonPartialToken::ArrayValue => self.partial_tokens.push(JsonPartialToken::ArrayValue),
-                        JsonPartialToken::BooleanValue { raw: _, value: _ } => self.is_error = true,
-                        JsonPartialToken::NullValue { raw: _ } => self.is_error = true,
-                        JsonPartialToken::UndefinedValue { raw: _ } => self.is_error = true,
-                        JsonPartialToken::StringValue { raw: _, value: _ } => self.is_error = true,
-                        JsonPartialToken::Root => self.partial_tokens.push(JsonPartialToken::Root),
-                        JsonPartialToken::NumberValue(raw_number) => {
-                            if raw_number.contains(".") {
-                                if let Ok(number) = raw_number.as_str().parse::<f64>() {
-                                    self.tokens.push_back(JsonToken::FloatValue { raw: raw_number, value: number });
-                                } else {
-                                    panic!("malformed number");
-                                }
-                            } else {
-                                if let Ok(number) = raw_number.as_str().parse::<isize>() {
-                                    self.tokens.push_back(JsonToken::IntegerValue { raw: raw_number, value: number });
-                                } else {
-                                    panic!("malformed number");
-                                }
-                            }
-                        }
-                        JsonPartialToken::Whitespace(whitespace) => {
-                            self.tokens.push_back(JsonToken::Whitespace(whitespace));
-                        }
-                    }
-                } else {
-                    self.is_error = true;
-                }
-
+                self.partial_tokens.push(partial_token);
                 self.tokens.push_back(JsonToken::NewLine(String::from(c)));
             }
-            '.' => {
-                if let Some(partial_token) = self.partial_tokens.pop() {
-                    match partial_token {
-                        JsonPartialToken::Array => self.is_error = true,
-                        JsonPartialToken::Object => self.is_error = true,
-                        JsonPartialToken::PropertyName => self.is_error = true,
-                        JsonPartialToken::PropertyValue => self.is_error = true,
-                        JsonPartialToken::ArrayValue => self.is_error = true,
-                        JsonPartialToken::BooleanValue { raw: _, value: _ } => self.is_error = true,
-                        JsonPartialToken::NullValue { raw: _ } => self.is_error = true,
-                        JsonPartialToken::UndefinedValue { raw: _ } => self.is_error = true,
-                        JsonPartialToken::StringValue { mut raw, mut value } => {
-                            raw.push(c);
-                            value.push(c);
-                            self.partial_tokens.push(JsonPartialToken::StringValue { raw, value });
-                        }
-                        JsonPartialToken::Root => self.is_error = true,
-                        JsonPartialToken::NumberValue(mut number) => {
-                            if number.contains(".") {
-                                panic!("malformed number");
-                            } else {
-                                number.push(c);
-                                self.partial_tokens.push(JsonPartialToken::NumberValue(number));
-                            }
-                        }
-                        JsonPartialToken::Whitespace(_) => self.is_error = true,
-                    }
-                } else {
-                    self.is_error = true;
+            '0'..='9' | '-' => {
+                if self.start_value(partial_token) {
+                    self.partial_tokens.push(JsonPartialToken::NumberValue(String::from(c)));
                 }
             }
-            '0' | '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9' => {
-                if let Some(partial_token) = self.partial_tokens.pop() {
-                    match partial_token {
-                        JsonPartialToken::Array => {
-                            self.partial_tokens.push(JsonPartialToken::Array);
-                            self.partial_tokens.push(JsonPartialToken::NumberValue(String::from(c)));
-                        }
-                        JsonPartialToken::Object => self.is_error = true,
-                        JsonPartialToken::PropertyName => self.is_error = true,
-                        JsonPartialToken::PropertyValue => self.partial_tokens.push(JsonPartialToken::NumberValue(String::from(c))),
-                        JsonPartialToken::ArrayValue => self.partial_tokens.push(JsonPartialToken::NumberValue(String::from(c))),
-                        JsonPartialToken::BooleanValue { raw: _, value: _ } => self.is_error = true,
-                        JsonPartialToken::NullValue { raw: _ } => self.is_error = true,
-                        JsonPartialToken::UndefinedValue { raw: _ } => self.is_error = true,
-                        JsonPartialToken::StringValue { mut raw, mut value } => {
-                            raw.push(c);
-                            value.push(c);
-                            self.partial_tokens.push(JsonPartialToken::StringValue { raw, value });
-                        }
-                        JsonPartialToken::Root => self.is_error = true,
-                        JsonPartialToken::NumberValue(mut number) => {
-                            number.push(c);
-                            self.partial_tokens.push(JsonPartialToken::NumberValue(number));
-                        }
-                        JsonPartialToken::Whitespace(whitespace) => {
-                            if let Some(partial_token) = self.partial_tokens.pop() {
-                                match partial_token {
-                                    JsonPartialToken::Array => {
-                                        self.tokens.push_back(JsonToken::Whitespace(whitespace));
-                                        self.partial_tokens.push(JsonPartialToken::Array);
-                                        self.partial_tokens.push(JsonPartialToken::NumberValue(String::from(c)));
-                                    }
-                                    JsonPartialToken::Object => self.is_error = true,
-                                    JsonPartialToken::PropertyName => self.is_error = true,
-                                    JsonPartialToken::PropertyValue => {
-                                        self.tokens.push_back(JsonToken::Whitespace(whitespace));
-                                        self.partial_tokens.push(JsonPartialToken::NumberValue(String::from(c)));
-                                    }
-                                    JsonPartialToken::ArrayValue => {
-                                        self.tokens.push_back(JsonToken::Whitespace(whitespace));
-                                        self.partial_tokens.push(JsonPartialToken::NumberValue(String::from(c)));
-                                    }
-                                    JsonPartialToken::BooleanValue { raw: _, value: _ } => self.is_error = true,
-                                    JsonPartialToken::NullValue { raw: _ } => self.is_error = true,
-                                    JsonPartialToken::UndefinedValue { raw: _ } => self.is_error = true,
-                                    JsonPartialToken::StringValue { raw: _, value: _ } => self.is_error = true,
-                                    JsonPartialToken::Root => self.is_error = true,
-                                    JsonPartialToken::NumberValue(_) => self.is_error = true,
-                                    JsonPartialToken::Whitespace(_) => self.is_error = true,
-                                }
-                            } else {
-                                self.is_error = true;
-                            }
-                        }
+            't' | 'f' | 'n' | 'u' => {
+                if self.start_value(partial_token) {
+                    match c {
+                        't' => self.partial_tokens.push(JsonPartialToken::BooleanValue { raw: String::from(c), value: true }),
+                        'f' => self.partial_tokens.push(JsonPartialToken::BooleanValue { raw: String::from(c), value: false }),
+                        'n' => self.partial_tokens.push(JsonPartialToken::NullValue { raw: String::from(c) }),
+                        _ => self.partial_tokens.push(JsonPartialToken::UndefinedValue { raw: String::from(c) }),
                     }
-                } else {
-                    self.is_error = true;
                 }
             }
-            't' | 'r' | 'u' | 'e' | 'f' | 'a' | 'l' | 's' | 'n' | 'd' | 'i' | 'T' | 'R' | 'U' | 'E' | 'F' | 'A' | 'L' | 'S' | 'N' | 'D' | 'I' => {
-                if let Some(partial_token) = self.partial_tokens.pop() {
-                    match partial_token {
-                        JsonPartialToken::Array => self.is_error = true,
-                        JsonPartialToken::Object => self.is_error = true,
-                        JsonPartialToken::PropertyName => self.is_error = true,
-                        JsonPartialToken::PropertyValue => match c {
-                            't' | 'T' => self.partial_tokens.push(JsonPartialToken::BooleanValue { raw: String::from(c), value: true }),
-                            'f' | 'F' => self.partial_tokens.push(JsonPartialToken::BooleanValue { raw: String::from(c), value: false }),
-                            'n' | 'N' => self.partial_tokens.push(JsonPartialToken::NullValue { raw: String::from(c) }),
-                            'u' | 'U' => self.partial_tokens.push(JsonPartialToken::UndefinedValue { raw: String::from(c) }),
-                            _ => self.is_error = true,
-                        },
-                        JsonPartialToken::ArrayValue => match c {
-                            't' | 'T' => self.partial_tokens.push(JsonPartialToken::BooleanValue { raw: String::from(c), value: true }),
-                            'f' | 'F' => self.partial_tokens.push(JsonPartialToken::BooleanValue { raw: String::from(c), value: false }),
-                            'n' | 'N' => self.partial_tokens.push(JsonPartialToken::NullValue { raw: String::from(c) }),
-                            'u' | 'U' => self.partial_tokens.push(JsonPartialToken::UndefinedValue { raw: String::from(c) }),
-                            _ => self.is_error = true,
-                        },
-                        JsonPartialToken::BooleanValue { mut raw, value } => {
-                            raw.push(c);
-                            if c == 'e' || c == 'E' {
-                                self.tokens.push_back(JsonToken::BooleanValue { raw, value });
-                            } else {
-                                self.partial_tokens.push(JsonPartialToken::BooleanValue { raw, value });
-                            }
-                        }
-                        JsonPartialToken::StringValue { mut raw, mut value } => {
-                            raw.push(c);
-                            value.push(c);
-                            self.partial_tokens.push(JsonPartialToken::StringValue { raw, value });
-                        }
-                        JsonPartialToken::NullValue { mut raw } => {
-                            raw.push(c);
-                            if raw == "null" {
-                                self.tokens.push_back(JsonToken::NullValue(raw));
-                            } else {
-                                self.partial_tokens.push(JsonPartialToken::NullValue { raw });
-                            }
-                        }
-                        JsonPartialToken::UndefinedValue { mut raw } => {
-                            raw.push(c);
-                            if raw == "undefined" {
-                                self.tokens.push_back(JsonToken::UndefinedValue(raw));
-                            } else {
-                                self.partial_tokens.push(JsonPartialToken::UndefinedValue { raw });
-                            }
-                        }
-                        JsonPartialToken::Root => self.is_error = true,
-                        JsonPartialToken::NumberValue(_) => self.is_error = true,
-                        JsonPartialToken::Whitespace(whitespace) => {
-                            self.tokens.push_back(JsonToken::Whitespace(whitespace));
-                            match c {
-                                't' | 'T' => self.partial_tokens.push(JsonPartialToken::BooleanValue { raw: String::from(c), value: true }),
-                                'f' | 'F' => self.partial_tokens.push(JsonPartialToken::BooleanValue { raw: String::from(c), value: false }),
-                                'n' | 'N' => self.partial_tokens.push(JsonPartialToken::NullValue { raw: String::from(c) }),
-                                'u' | 'U' => self.partial_tokens.push(JsonPartialToken::UndefinedValue { raw: String::from(c) }),
-                                _ => self.is_error = true,
-                            }
-                        }
-                    }
+            _ => self.push_error(partial_token),
+        }
+    }
+
+    /// Checks a value can start where the partial token says. The root is replaced with `End` once its value starts, as
+    /// only whitespace can follow it.
+    fn start_value(&mut self, partial_token: JsonPartialToken) -> bool {
+        match partial_token {
+            JsonPartialToken::Root => {
+                self.partial_tokens.push(JsonPartialToken::End);
+                true
+            }
+            JsonPartialToken::PropertyValue | JsonPartialToken::FirstArrayValue | JsonPartialToken::ArrayValue => true,
+            _ => {
+                self.push_error(partial_token);
+                false
+            }
+        }
+    }
+
+    fn push_string_char(&mut self, mut raw: String, mut value: String, c: char) {
+        match c {
+            '"' => {
+                raw.push(c);
+
+                if let Some(JsonPartialToken::PropertyName) = self.partial_tokens.last() {
+                    self.partial_tokens.pop();
+                    self.tokens.push_back(JsonToken::PropertyName { raw, name: value });
+                    self.partial_tokens.push(JsonPartialToken::KeyValueDelimiter);
                 } else {
-                    self.is_error = true;
+                    self.tokens.push_back(JsonToken::StringValue { raw, value });
                 }
             }
+            '\\' => {
+                raw.push(c);
+                self.partial_tokens.push(JsonPartialToken::StringEscape { raw, value, escape: String::from(c) });
+            }
+            '\n' => {
+                self.partial_tokens.push(JsonPartialToken::StringValue { raw, value });
+                self.set_error("a closing '\"'");
+            }
+            '\u{0}'..='\u{1f}' => {
+                self.partial_tokens.push(JsonPartialToken::StringValue { raw, value });
+                self.set_error("an escape sequence in place of the control character");
+            }
             _ => {
-                if let Some(partial_token) = self.partial_tokens.pop() {
-                    match partial_token {
-                        JsonPartialToken::Array => self.is_error = true,
-                        JsonPartialToken::Object => self.is_error = true,
-                        JsonPartialToken::PropertyName => self.is_error = true,
-                        JsonPartialToken::PropertyValue => self.is_error = true,
-                        JsonPartialToken::ArrayValue => self.is_error = true,
-                        JsonPartialToken::BooleanValue { raw: _, value: _ } => self.is_error = true,
-                        JsonPartialToken::NullValue { raw: _ } => self.is_error = true,
-                        JsonPartialToken::UndefinedValue { raw: _ } => self.is_error = true,
-                        JsonPartialToken::StringValue { mut raw, mut value } => {
-                            raw.push(c);
-                            value.push(c);
-                            self.partial_tokens.push(JsonPartialToken::StringValue { raw, value });
+                raw.push(c);
+                value.push(c);
+                self.partial_tokens.push(JsonPartialToken::StringValue { raw, value });
+            }
+        }
+    }
+
+    /// Decodes an escape sequence within a string. `escape` holds the sequence read so far, starting with the backslash. A
+    /// `\u` escape for a high surrogate is held until the `\u` escape for its low surrogate has been read as well, so the
+    /// pair can be decoded into a single character.
+    fn push_escape_char(&mut self, mut raw: String, mut value: String, mut escape: String, c: char) {
+        match (escape.len(), c) {
+            (1, '"' | '\\' | '/' | 'b' | 'f' | 'n' | 'r' | 't') => {
+                raw.push(c);
+                value.push(match c {
+                    'b' => '\u{8}',
+                    'f' => '\u{c}',
+                    'n' => '\n',
+                    'r' => '\r',
+                    't' => '\t',
+                    _ => c,
+                });
+                self.partial_tokens.push(JsonPartialToken::StringValue { raw, value });
+            }
+            (1 | 7, 'u') | (6, '\\') => {
+                raw.push(c);
+                escape.push(c);
+                self.partial_tokens.push(JsonPartialToken::StringEscape { raw, value, escape });
+            }
+            (2..=5 | 8..=11, _) if c.is_ascii_hexdigit() => {
+                raw.push(c);
+                escape.push(c);
+
+                let code_unit = |start: usize| u32::from_str_radix(&escape[start..start + 4], 16).unwrap_or_default();
+
+                match escape.len() {
+                    6 if (0xd800..0xdc00).contains(&code_unit(2)) => {
+                        self.partial_tokens.push(JsonPartialToken::StringEscape { raw, value, escape });
+                        return;
+                    }
+                    6 => value.push(char::from_u32(code_unit(2)).unwrap_or(char::REPLACEMENT_CHARACTER)),
+                    12 => {
+                        let (high, low) = (code_unit(2), code_unit(8));
+
+                        if (0xdc00..0xe000).contains(&low) {
+                            value.push(char::from_u32(0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)).unwrap_or(char::REPLACEMENT_CHARACTER));
+                        } else {
+                            value.push(char::REPLACEMENT_CHARACTER);
+                            value.push(char::from_u32(low).unwrap_or(char::REPLACEMENT_CHARACTER));
                         }
-                        JsonPartialToken::Root => self.is_error = true,
-                        JsonPartialToken::NumberValue(_) => self.is_error = true,
-                        JsonPartialToken::Whitespace(_) => self.is_error = true,
                     }
-                } else {
-                    self.is_error = true;
+                    _ => {
+                        self.partial_tokens.push(JsonPartialToken::StringEscape { raw, value, escape });
+                        return;
+                    }
                 }
+
+                self.partial_tokens.push(JsonPartialToken::StringValue { raw, value });
+            }
+            // A high surrogate without a low surrogate after it can't be decoded so it's replaced, and what follows it is
+            // read as usual.
+            (6, _) => {
+                value.push(char::REPLACEMENT_CHARACTER);
+                self.push_string_char(raw, value, c);
+            }
+            (7, _) => {
+                value.push(char::REPLACEMENT_CHARACTER);
+                self.push_escape_char(raw, value, String::from("\\"), c);
+            }
+            _ => {
+                self.set_error(if escape.len() == 1 { "an escape character such as 'n' or 'u'" } else { "a hexadecimal digit" });
+                self.partial_tokens.push(JsonPartialToken::StringEscape { raw, value, escape });
             }
         }
+    }
+
+    fn push_keyword_char(&mut self, partial_token: JsonPartialToken, c: char) {
+        let (mut raw, keyword) = match partial_token {
+            JsonPartialToken::BooleanValue { raw, value: true } => (raw, "true"),
+            JsonPartialToken::BooleanValue { raw, value: false } => (raw, "false"),
+            JsonPartialToken::NullValue { raw } => (raw, "null"),
+            JsonPartialToken::UndefinedValue { raw } => (raw, "undefined"),
+            _ => return self.push_error(partial_token),
+        };
+
+        raw.push(c);
 
-        if self.partial_tokens.len() == 0 {
-            println!("empty '{}'", c);
+        if !keyword.starts_with(raw.as_str()) {
+            self.set_error(format!("'{}'", keyword).as_str());
         }
 
-        Ok(())
+        if raw.len() < keyword.len() {
+            match keyword {
+                "true" => self.partial_tokens.push(JsonPartialToken::BooleanValue { raw, value: true }),
+                "false" => self.partial_tokens.push(JsonPartialToken::BooleanValue { raw, value: false }),
+                "null" => self.partial_tokens.push(JsonPartialToken::NullValue { raw }),
+                _ => self.partial_tokens.push(JsonPartialToken::UndefinedValue { raw }),
+            }
+        } else {
+            match keyword {
+                "true" => self.tokens.push_back(JsonToken::BooleanValue { raw, value: true }),
+                "false" => self.tokens.push_back(JsonToken::BooleanValue { raw, value: false }),
+                "null" => self.tokens.push_back(JsonToken::NullValue(raw)),
+                _ => self.tokens.push_back(JsonToken::UndefinedValue(raw)),
+            }
+        }
+    }
+
+    /// Finishes a number. Integers too big for an `isize` are kept as their raw text rather than being rounded or rejected.
+    fn push_number(&mut self, raw_number: String) {
+        if !is_number(&raw_number) {
+            self.set_number_error(&raw_number);
+        } else if raw_number.contains(['.', 'e', 'E']) {
+            match raw_number.as_str().parse::<f64>() {
+                Ok(number) => self.tokens.push_back(JsonToken::FloatValue { raw: raw_number, value: number }),
+                Err(_) => self.set_number_error(&raw_number),
+            }
+        } else {
+            match raw_number.as_str().parse::<isize>() {
+                Ok(number) => self.tokens.push_back(JsonToken::IntegerValue { raw: raw_number, value: number }),
+                Err(_) => self.tokens.push_back(JsonToken::BigIntegerValue(raw_number)),
+            }
+        }
+    }
+
+    /// The number has ended so the position is just past it, but the error should point at its start.
+    fn set_number_error(&mut self, raw_number: &str) {
+        let position = JsonPosition {
+            line: self.position.line,
+            column: self.position.column - raw_number.chars().count(),
+            offset: self.position.offset - raw_number.len(),
+        };
+
+        self.set_error_at("a number", format!("'{}'", raw_number), position);
     }
+
+    /// Flags the character as unexpected while keeping the partial token so the container stack stays intact.
+    fn push_error(&mut self, partial_token: JsonPartialToken) {
+        self.set_error(expected(&partial_token));
+        self.partial_tokens.push(partial_token);
+    }
+
+    /// Records the character being lexed as unexpected, or the end of the input if it is being closed.
+    fn set_error(&mut self, expected: &str) {
+        let found = match self.current_char {
+            Some('\n') => String::from("a new line"),
+            Some(c) => format!("'{}'", c.escape_debug()),
+            None => String::from("the end of the input"),
+        };
+
+        self.set_error_at(expected, found, self.position);
+    }
+
+    /// Only the first error is kept as anything after it is likely to be a knock-on effect of it.
+    fn set_error_at(&mut self, expected: &str, found: String, position: JsonPosition) {
+        if self.error.is_none() {
+            self.error = Some(JsonLexerError {
+                position,
+                expected: String::from(expected),
+                found,
+                snippet: String::from(self.current_line.trim_end_matches('\r')),
+            });
+        }
+    }
+}
+
+/// Describes what can come next when the given partial token is on the top of the stack.
+fn expected(partial_token: &JsonPartialToken) -> &'static str {
+    match partial_token {
+        JsonPartialToken::Array => "',' or ']'",
+        JsonPartialToken::Object => "',' or '}'",
+        JsonPartialToken::FirstPropertyName => "a property name or '}'",
+        JsonPartialToken::PropertyName => "a property name",
+        JsonPartialToken::KeyValueDelimiter => "':'",
+        JsonPartialToken::PropertyValue => "a value",
+        JsonPartialToken::FirstArrayValue => "a value or ']'",
+        JsonPartialToken::ArrayValue => "a value",
+        JsonPartialToken::BooleanValue { raw: _, value: true } => "'true'",
+        JsonPartialToken::BooleanValue { raw: _, value: false } => "'false'",
+        JsonPartialToken::StringValue { raw: _, value: _ } => "a closing '\"'",
+        JsonPartialToken::StringEscape { raw: _, value: _, escape: _ } => "a closing '\"'",
+        JsonPartialToken::NullValue { raw: _ } => "'null'",
+        JsonPartialToken::UndefinedValue { raw: _ } => "'undefined'",
+        JsonPartialToken::Root => "a value",
+        JsonPartialToken::End => "the end of the input",
+        JsonPartialToken::NumberValue(_) => "a number",
+        JsonPartialToken::Whitespace(_) => "a value",
+    }
+}
+
+/// Checks a number against the JSON number grammar, `-?(0|[1-9][0-9]*)(\.[0-9]+)?([eE][+-]?[0-9]+)?`.
+fn is_number(raw_number: &str) -> bool {
+    fn digits(number: &[u8]) -> usize {
+        number.iter().take_while(|b| b.is_ascii_digit()).count()
+    }
+
+    let mut number = raw_number.as_bytes();
+
+    if let [b'-', rest @ ..] = number {
+        number = rest;
+    }
+
+    match number {
+        [b'0', rest @ ..] => number = rest,
+        [b'1'..=b'9', ..] => number = &number[digits(number)..],
+        _ => return false,
+    }
+
+    if let [b'.', rest @ ..] = number {
+        match digits(rest) {
+            0 => return false,
+            fraction => number = &rest[fraction..],
+        }
+    }
+
+    if let [b'e' | b'E', rest @ ..] = number {
+        let rest = match rest {
+            [b'+' | b'-', rest @ ..] => rest,
+            _ => rest,
+        };
+
+        match digits(rest) {
+            0 => return false,
+            exponent => number = &rest[exponent..],
+        }
+    }
+
+    number.is_empty()
 }
 
 #[cfg(test)]
@@ -971,7 +717,7 @@ mod tests {
                     if is_first {
                         is_first = false;
                     } else {
-                        tokenized.push_str(format!(" -> ").as_str());
+                        tokenized.push_str(" -> ");
                     }
                     tokenized.push_str(format!("{}", token).as_str());
                     write_token(tokenized, token);
@@ -999,6 +745,9 @@ mod tests {
             JsonToken::FloatValue { raw, value } => {
                 tokenized.push_str(format!("({},{})", raw, value).as_str());
             }
+            JsonToken::BigIntegerValue(raw) => {
+                tokenized.push_str(format!("({})", raw).as_str());
+            }
             JsonToken::NullValue(raw) => {
                 tokenized.push_str(format!("({})", raw).as_str());
             }
@@ -1021,7 +770,7 @@ mod tests {
                 tokenized.push_str(format!("({})", whitespace).as_str());
             }
             JsonToken::NewLine(_) => {
-                tokenized.push_str(format!("").as_str());
+                tokenized.push_str("");
             }
             JsonToken::ArrayItemDelimiter(delimiter) => {
                 tokenized.push_str(format!("({})", delimiter).as_str());
@@ -1044,17 +793,222 @@ mod tests {
         let mut tokenized = String::new();
 
         for c in TABBED_JSON_SAMPLE.chars() {
-            if let Ok(_) = json_lexer.push_char(c) {
-                assert!(true);
-            } else {
-                assert!(false);
-            }
+            assert!(json_lexer.push_char(c).is_ok());
 
             is_first = write_tokens(is_first, &mut json_lexer, &mut tokenized);
         }
 
-        json_lexer.close();
+        json_lexer.close().unwrap();
 
         assert_eq!(tokenized, String::from(TOKENIZED_JSON.clone()));
     }
+
+    /// Lexes `json` and returns the raw text and decoded value of each property name and string in it.
+    fn strings(json: &str) -> Vec<(String, String)> {
+        let mut json_lexer = JsonStreamLexer::new();
+        let mut strings = Vec::new();
+
+        for c in json.chars() {
+            assert!(json_lexer.push_char(c).is_ok());
+        }
+
+        json_lexer.close().unwrap();
+
+        while let JsonStreamStatus::Token(token) = json_lexer.pop_token() {
+            match token {
+                JsonToken::PropertyName { raw, name } => strings.push((raw, name)),
+                JsonToken::StringValue { raw, value } => strings.push((raw, value)),
+                _ => {}
+            }
+        }
+
+        strings
+    }
+
+    #[test]
+    fn test_string_escapes() {
+        let json = r#"{ "say \"hi\"": "a\"b\\c\/d\be\ff\ng\rh\ti", "u": "caf\u00e9 \uD83C\udf69 🍩", "bad": "\ud83c\n" }"#;
+
+        let expected = vec![
+            (r#""say \"hi\"""#, "say \"hi\""),
+            (r#""a\"b\\c\/d\be\ff\ng\rh\ti""#, "a\"b\\c/d\u{8}e\u{c}f\ng\rh\ti"),
+            (r#""u""#, "u"),
+            (r#""caf\u00e9 \uD83C\udf69 🍩""#, "café 🍩 🍩"),
+            (r#""bad""#, "bad"),
+            (r#""\ud83c\n""#, "\u{fffd}\n"),
+        ];
+
+        let actual = strings(json);
+
+        assert_eq!(actual.len(), expected.len());
+
+        for ((raw, value), (expected_raw, expected_value)) in actual.iter().zip(expected) {
+            assert_eq!(raw, expected_raw);
+            assert_eq!(value, expected_value);
+        }
+    }
+
+    #[test]
+    fn test_invalid_string_escapes() {
+        for json in [r#"["\x"]"#, r#"["\u00g0"]"#, r#"["\"]"#] {
+            let mut json_lexer = JsonStreamLexer::new();
+
+            for c in json.chars() {
+                let _ = json_lexer.push_char(c);
+            }
+
+            assert!(json_lexer.close().is_err(), "{}", json);
+        }
+    }
+
+    #[test]
+    fn test_numbers() {
+        let json = "[0, -0, 12, -7, 0.5, -0.5E-3, 1e10, 2E+2, 123456789012345678901234567890, -98765432109876543210]";
+
+        let mut json_lexer = JsonStreamLexer::new();
+        let mut tokenized = String::new();
+
+        for c in json.chars() {
+            assert!(json_lexer.push_char(c).is_ok());
+        }
+
+        json_lexer.close().unwrap();
+
+        while let JsonStreamStatus::Token(token) = json_lexer.pop_token() {
+            if !matches!(token, JsonToken::ArrayOpen(_) | JsonToken::ArrayClose(_) | JsonToken::ArrayItemDelimiter(_) | JsonToken::Whitespace(_)) {
+                tokenized.push(' ');
+                tokenized.push_str(token.to_string().as_str());
+                write_token(&mut tokenized, token);
+            }
+        }
+
+        assert_eq!(
+            tokenized,
+            " IntegerValue(0,0) IntegerValue(-0,0) IntegerValue(12,12) IntegerValue(-7,-7) FloatValue(0.5,0.5) FloatValue(-0.5E-3,-0.0005) FloatValue(1e10,10000000000) FloatValue(2E+2,200) BigIntegerValue(123456789012345678901234567890) BigIntegerValue(-98765432109876543210)"
+        );
+    }
+
+    #[test]
+    fn test_invalid_numbers() {
+        for number in ["01", "-", "1.", ".5", "1e", "1e+", "--1", "1-2", "+1", "1.2.3", "0x10"] {
+            let mut json_lexer = JsonStreamLexer::new();
+            let json = format!("[{}]", number);
+
+            for c in json.chars() {
+                let _ = json_lexer.push_char(c);
+            }
+
+            assert!(json_lexer.close().is_err(), "{}", number);
+        }
+    }
+
+    fn lex_error(json: &str) -> JsonLexerError {
+        let mut json_lexer = JsonStreamLexer::new();
+
+        for c in json.chars() {
+            if let Err(error) = json_lexer.push_char(c) {
+                return error;
+            }
+        }
+
+        json_lexer.close().unwrap_err()
+    }
+
+    #[test]
+    fn test_scalar_roots() {
+        for json in ["42", " -1.5 ", "\"a\"", "true", "null\n"] {
+            let mut json_lexer = JsonStreamLexer::new();
+
+            for c in json.chars() {
+                assert!(json_lexer.push_char(c).is_ok(), "{}", json);
+            }
+
+            assert!(json_lexer.close().is_ok(), "{}", json);
+        }
+
+        assert_eq!(lex_error("x").expected, "a value");
+    }
+
+    #[test]
+    fn test_token_spans() {
+        let json = "{\r\n\t\"é\": [1, true]\n}";
+        let mut json_lexer = JsonStreamLexer::new();
+
+        for c in json.chars() {
+            json_lexer.push_char(c).unwrap();
+        }
+
+        json_lexer.close().unwrap();
+
+        let mut spans = Vec::new();
+
+        while let Some((token, span)) = json_lexer.pop_token_with_span() {
+            assert_eq!(&json[span.start.offset..span.end.offset], token.raw());
+
+            spans.push((token.raw().to_string(), (span.start.line, span.start.column), (span.end.line, span.end.column)));
+        }
+
+        let span = |raw: &str| spans.iter().find(|(token, _, _)| token == raw).map(|(_, start, end)| (*start, *end)).unwrap();
+
+        assert_eq!(span("\r\n"), ((1, 2), (2, 1)));
+        assert_eq!(span("\"é\""), ((2, 2), (2, 5)));
+        assert_eq!(span("true"), ((2, 11), (2, 15)));
+        assert_eq!(span("}"), ((3, 1), (3, 2)));
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = lex_error("{\n\t\"a\": 1,\n\t\"é\": 2 x\n}");
+
+        assert_eq!(error.position, JsonPosition { line: 3, column: 9, offset: 20 });
+        assert_eq!(error.expected, "',' or '}'");
+        assert_eq!(error.found, "'x'");
+        assert_eq!(error.to_string(), "expected ',' or '}' but found 'x' at line 3, column 9 (byte 20)\n\t\"é\": 2 x\n\t       ^");
+
+        let error = lex_error("[1, -01]");
+
+        assert_eq!(error.position, JsonPosition { line: 1, column: 5, offset: 4 });
+        assert_eq!(error.expected, "a number");
+        assert_eq!(error.found, "'-01'");
+
+        let error = lex_error("{ \"a\": \"b\n\" }");
+
+        assert_eq!(error.position, JsonPosition { line: 1, column: 10, offset: 9 });
+        assert_eq!(error.expected, "a closing '\"'");
+        assert_eq!(error.found, "a new line");
+    }
+
+    #[test]
+    fn test_invalid_documents() {
+        for (json, expected, found) in [
+            ("{\"a\" 1}", "':'", "'1'"),
+            ("{\"a\"::1}", "a value", "':'"),
+            ("{\"a\":1,}", "a property name", "'}'"),
+            ("[1,]", "a value", "']'"),
+            ("1 2", "the end of the input", "'2'"),
+            ("{} {}", "the end of the input", "'{'"),
+            ("\"x\" 3", "the end of the input", "'3'"),
+            ("[1]\n[2]", "the end of the input", "'['"),
+            ("TRUE", "a value", "'T'"),
+            ("[Null]", "a value or ']'", "'N'"),
+            ("[tRUE]", "'true'", "'R'"),
+            ("\"a\tb\"", "an escape sequence in place of the control character", "'\\t'"),
+            ("[\"\u{1}\"]", "an escape sequence in place of the control character", "'\\u{1}'"),
+        ] {
+            let error = lex_error(json);
+
+            assert_eq!((error.expected.as_str(), error.found.as_str()), (expected, found), "{}", json);
+        }
+    }
+
+    #[test]
+    fn test_end_of_input_errors() {
+        for (json, expected) in [("{ \"a\": [1, 2", "',' or ']'"), ("{ \"a\"", "':'"), ("[nul", "'null'"), ("[\"a", "a closing '\"'")] {
+            let error = lex_error(json);
+
+            assert_eq!(error.expected, expected);
+            assert_eq!(error.found, "the end of the input");
+            assert_eq!(error.position.offset, json.len());
+        }
+    }
 }
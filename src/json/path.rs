@@ -1,13 +1,21 @@
+use std::error::Error;
 use std::fmt;
 
+use super::filter::JsonFilter;
+use super::lexer::write_caret;
+
 pub enum JsonPathOperator {
-    ObjectRoot,
-    ArrayRoot(isize),
+    Root,
     MemberAccess(String),
     DeepScanMemberAccess(String),
     ArrayIndex(isize),
-    ArraySlice(isize, isize),
-    FilterExpression(String),
+    ArraySlice(Option<isize>, Option<isize>, Option<isize>),
+    FilterExpression(JsonFilter),
+    Wildcard,
+    DeepScanWildcard,
+    /// Any other selector between the brackets after `..`, such as `..[0]` or `..[0,1]`, applied at every depth.
+    DeepScan(Box<JsonPathOperator>),
+    Union(Vec<JsonPathOperator>),
 }
 
 impl fmt::Display for JsonPathOperator {
@@ -15,40 +23,58 @@ impl fmt::Display for JsonPathOperator {
         let mut output = String::new();
 
         match self {
-            JsonPathOperator::ObjectRoot => {
-                output.push_str("ObjectRoot");
-            }
-            JsonPathOperator::ArrayRoot(index) => {
-                output.push_str("ArrayRoot(");
-                output.push_str(index.to_string().as_str());
-                output.push_str(")");
+            JsonPathOperator::Root => {
+                output.push_str("Root");
             }
             JsonPathOperator::MemberAccess(name) => {
                 output.push_str("MemberAccess(");
                 output.push_str(name);
-                output.push_str(")");
+                output.push(')');
             }
             JsonPathOperator::DeepScanMemberAccess(name) => {
                 output.push_str("DeepScanMemberAccess(");
                 output.push_str(name);
-                output.push_str(")");
+                output.push(')');
             }
             JsonPathOperator::ArrayIndex(index) => {
                 output.push_str("ArrayIndex(");
                 output.push_str(index.to_string().as_str());
-                output.push_str(")");
+                output.push(')');
             }
-            JsonPathOperator::ArraySlice(start, end) => {
+            JsonPathOperator::ArraySlice(start, end, step) => {
+                // Bounds that were left out are left empty.
                 output.push_str("ArraySlice(");
-                output.push_str(start.to_string().as_str());
-                output.push_str(",");
-                output.push_str(end.to_string().as_str());
-                output.push_str(")");
+                output.push_str(start.map(|start| start.to_string()).unwrap_or_default().as_str());
+                output.push(',');
+                output.push_str(end.map(|end| end.to_string()).unwrap_or_default().as_str());
+
+                if let Some(step) = step {
+                    output.push(',');
+                    output.push_str(step.to_string().as_str());
+                }
+
+                output.push(')');
             }
             JsonPathOperator::FilterExpression(filter) => {
                 output.push_str("FilterExpression(");
-                output.push_str(filter);
-                output.push_str(")");
+                output.push_str(filter.to_string().as_str());
+                output.push(')');
+            }
+            JsonPathOperator::Wildcard => {
+                output.push_str("Wildcard");
+            }
+            JsonPathOperator::DeepScanWildcard => {
+                output.push_str("DeepScanWildcard");
+            }
+            JsonPathOperator::DeepScan(selector) => {
+                output.push_str("DeepScan(");
+                output.push_str(selector.to_string().as_str());
+                output.push(')');
+            }
+            JsonPathOperator::Union(selectors) => {
+                output.push_str("Union(");
+                output.push_str(selectors.iter().map(|selector| selector.to_string()).collect::<Vec<String>>().join(",").as_str());
+                output.push(')');
             }
         };
 
@@ -58,21 +84,20 @@ impl fmt::Display for JsonPathOperator {
 
 enum JsonPathPartialOperator {
     Root,
-    OpenRootBracket,
-    ArrayRootIndex(String),
     PreMemberAccess,
     MemberAccess(String),
     DeepScanMemberAccess(String),
     OpenBracket,
     ArrayIndex(String),
     ArraySlice(String),
-    OpenFilter,
-    FilterExpression { depth: isize, expr: String },
-    EscapeCharacter(),
+    FilterExpression { depth: isize, quote: Option<char>, expr: String },
+    EscapeCharacter(String),
     OpenSingleQuotes(String),
     OpenDoubleQuotes(String),
     ClosedSingleQuotes(String),
     ClosedDoubleQuotes(String),
+    BracketedWildcard,
+    Union(Vec<JsonPathOperator>),
 }
 
 pub struct JsonPathIterator<'a> {
@@ -81,7 +106,7 @@ pub struct JsonPathIterator<'a> {
 }
 
 impl<'a> JsonPathIterator<'a> {
-    fn from(path: &JsonPath) -> JsonPathIterator {
+    fn from(path: &JsonPath) -> JsonPathIterator<'_> {
         JsonPathIterator {
             path_data: &path.operations,
             current_index: 0,
@@ -103,22 +128,102 @@ impl<'a> Iterator for JsonPathIterator<'a> {
     }
 }
 
+/// Where a JSON path or pointer stopped making sense, counted in characters from the start of it, along with what was
+/// expected there.
+#[derive(Clone, Debug, PartialEq)]
+pub struct JsonPathError {
+    pub path: String,
+    pub position: usize,
+    pub expected: String,
+    pub found: String,
+}
+
+impl fmt::Display for JsonPathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "expected {} but found {} at position {} of the path", self.expected, self.found, self.position)?;
+        write_caret(f, &self.path, self.position)
+    }
+}
+
+impl Error for JsonPathError {}
+
 pub struct JsonPath {
     path: String,
     operations: Vec<JsonPathOperator>,
     partial_operations: Vec<JsonPathPartialOperator>,
+    non_standard: Option<String>,
+    position: usize,
+    error: Option<JsonPathError>,
 }
 
 impl JsonPath {
+    /// Reads a JSON path, panicking if it isn't valid. Use `parse` to get the error instead.
+    #[allow(dead_code)]
     pub fn from(path: &str) -> JsonPath {
-        let mut json_path = JsonPath {
+        JsonPath::parse(path).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Reads a JSON path, returning an error pointing at the first part of it that doesn't make sense.
+    pub fn parse(path: &str) -> Result<JsonPath, JsonPathError> {
+        let mut json_path = JsonPath::new(path);
+        json_path.tokenize();
+
+        match json_path.error.take() {
+            Some(err) => Err(err),
+            None => Ok(json_path),
+        }
+    }
+
+    /// Reads an RFC 6901 JSON pointer, such as `/batters/batter/1/type`, as the same operators a JSON path would use. A
+    /// pointer doesn't say whether a token of digits is an array index or a member name, so it becomes a union of both and
+    /// matches whichever fits the value it's applied to. An empty pointer is the whole document.
+    pub fn parse_pointer(pointer: &str) -> Result<JsonPath, JsonPathError> {
+        let mut json_path = JsonPath::new(pointer);
+        json_path.operations.push(JsonPathOperator::Root);
+
+        if pointer.is_empty() {
+            return Ok(json_path);
+        }
+
+        let Some(tokens) = pointer.strip_prefix('/') else {
+            json_path.set_error("'/'");
+            return Err(json_path.error.unwrap());
+        };
+
+        for token in tokens.split('/') {
+            json_path.position += 1;
+
+            let name = match unescape_pointer_token(token) {
+                Ok(name) => name,
+                Err(escape) => {
+                    let position = json_path.position + escape + 1;
+                    json_path.set_error_at(position, "'0' or '1' after '~'", json_path.found_at(position));
+                    return Err(json_path.error.unwrap());
+                }
+            };
+
+            json_path.position += token.chars().count();
+
+            match parse_pointer_index(&name) {
+                Some(index) => json_path
+                    .operations
+                    .push(JsonPathOperator::Union(vec![JsonPathOperator::MemberAccess(name), JsonPathOperator::ArrayIndex(index)])),
+                None => json_path.operations.push(JsonPathOperator::MemberAccess(name)),
+            }
+        }
+
+        Ok(json_path)
+    }
+
+    fn new(path: &str) -> JsonPath {
+        JsonPath {
             path: String::from(path),
             operations: Vec::new(),
             partial_operations: Vec::new(),
-        };
-        json_path.tokenize();
-
-        json_path
+            non_standard: None,
+            position: 0,
+            error: None,
+        }
     }
 
     #[allow(dead_code)]
@@ -130,41 +235,86 @@ impl JsonPath {
         &self.operations
     }
 
+    /// Describes the first part of the path that RFC 9535 doesn't allow but which is accepted anyway, such as the `.` in
+    /// `$.[0]`, or `None` if the path is standard JSONPath.
+    pub fn non_standard(&self) -> Option<&str> {
+        self.non_standard.as_deref()
+    }
+
     fn tokenize(&mut self) {
         let mut terminated_path = self.path.clone();
-        terminated_path.push_str("\n");
+        terminated_path.push('\n');
+
+        for (position, c) in terminated_path.chars().enumerate() {
+            if self.error.is_some() {
+                return;
+            }
+
+            self.position = position;
+
+            // An escape sequence in a quoted name is read on its own, as every character means something different in one.
+            if let Some(JsonPathPartialOperator::EscapeCharacter(_)) = self.partial_operations.last() {
+                if let Some(JsonPathPartialOperator::EscapeCharacter(escape)) = self.partial_operations.pop() {
+                    self.push_escape_char(escape, c);
+                }
+
+                continue;
+            }
 
-        for c in terminated_path.chars() {
             match c {
                 '$' => {
-                    if let None = self.partial_operations.pop() {
+                    if let Some(partial_operation) = self.partial_operations.pop() {
+                        match partial_operation {
+                            JsonPathPartialOperator::FilterExpression { depth, quote, expr } => self.push_filter_char(depth, quote, expr, c),
+                            JsonPathPartialOperator::OpenSingleQuotes(mut name) => {
+                                name.push(c);
+                                self.partial_operations.push(JsonPathPartialOperator::OpenSingleQuotes(name));
+                            }
+                            JsonPathPartialOperator::OpenDoubleQuotes(mut name) => {
+                                name.push(c);
+                                self.partial_operations.push(JsonPathPartialOperator::OpenDoubleQuotes(name));
+                            }
+                            partial_operation => self.set_error(expected(&partial_operation)),
+                        }
+                    } else if self.operations.is_empty() {
                         self.partial_operations.push(JsonPathPartialOperator::Root);
                     } else {
-                        todo!();
+                        self.set_error(self.expected_next());
                     }
                 }
                 '.' => {
                     if let Some(partial_operation) = self.partial_operations.pop() {
                         match partial_operation {
                             JsonPathPartialOperator::Root => {
-                                self.operations.push(JsonPathOperator::ObjectRoot);
+                                self.operations.push(JsonPathOperator::Root);
                                 self.partial_operations.push(JsonPathPartialOperator::PreMemberAccess);
                             }
                             JsonPathPartialOperator::PreMemberAccess => self.partial_operations.push(JsonPathPartialOperator::DeepScanMemberAccess(String::new())),
+                            // `..` always needs something to scan for, even where other non-standard paths are accepted.
+                            JsonPathPartialOperator::DeepScanMemberAccess(name) if name.is_empty() => self.set_error(expected(&JsonPathPartialOperator::DeepScanMemberAccess(name))),
                             JsonPathPartialOperator::MemberAccess(name) => {
+                                self.check_member_name(&name);
                                 self.operations.push(JsonPathOperator::MemberAccess(name));
                                 self.partial_operations.push(JsonPathPartialOperator::PreMemberAccess);
                             }
                             JsonPathPartialOperator::DeepScanMemberAccess(name) => {
+                                self.check_member_name(&name);
                                 self.operations.push(JsonPathOperator::DeepScanMemberAccess(name));
                                 self.partial_operations.push(JsonPathPartialOperator::PreMemberAccess);
                             }
-                            JsonPathPartialOperator::FilterExpression { depth, mut expr } => {
-                                expr.push(c);
-                                self.partial_operations.push(JsonPathPartialOperator::FilterExpression { depth, expr })
+                            JsonPathPartialOperator::FilterExpression { depth, quote, expr } => self.push_filter_char(depth, quote, expr, c),
+                            JsonPathPartialOperator::OpenSingleQuotes(mut name) => {
+                                name.push(c);
+                                self.partial_operations.push(JsonPathPartialOperator::OpenSingleQuotes(name));
                             }
-                            _ => todo!(),
+                            JsonPathPartialOperator::OpenDoubleQuotes(mut name) => {
+                                name.push(c);
+                                self.partial_operations.push(JsonPathPartialOperator::OpenDoubleQuotes(name));
+                            }
+                            partial_operation => self.set_error(expected(&partial_operation)),
                         }
+                    } else if self.operations.is_empty() {
+                        self.set_error(self.expected_next());
                     } else {
                         self.partial_operations.push(JsonPathPartialOperator::PreMemberAccess);
                     }
@@ -172,17 +322,43 @@ impl JsonPath {
                 '[' => {
                     if let Some(partial_operation) = self.partial_operations.pop() {
                         match partial_operation {
-                            JsonPathPartialOperator::Root => self.partial_operations.push(JsonPathPartialOperator::OpenRootBracket),
+                            JsonPathPartialOperator::Root => {
+                                self.operations.push(JsonPathOperator::Root);
+                                self.partial_operations.push(JsonPathPartialOperator::OpenBracket);
+                            }
                             JsonPathPartialOperator::MemberAccess(name) => {
+                                self.check_member_name(&name);
                                 self.operations.push(JsonPathOperator::MemberAccess(name));
                                 self.partial_operations.push(JsonPathPartialOperator::OpenBracket);
                             }
-                            JsonPathPartialOperator::FilterExpression { depth, mut expr } => {
-                                expr.push(c);
-                                self.partial_operations.push(JsonPathPartialOperator::FilterExpression { depth, expr })
+                            // Brackets straight after a `.` were once needed for root arrays, as in `$.[0]`, and are still accepted.
+                            JsonPathPartialOperator::PreMemberAccess => {
+                                self.set_non_standard("'.' before '['");
+                                self.partial_operations.push(JsonPathPartialOperator::OpenBracket);
+                            }
+                            // The name of a deep scan can be given in brackets, e.g. `..['name']`, so the scan is kept underneath them.
+                            JsonPathPartialOperator::DeepScanMemberAccess(name) if name.is_empty() => {
+                                self.partial_operations.push(JsonPathPartialOperator::DeepScanMemberAccess(name));
+                                self.partial_operations.push(JsonPathPartialOperator::OpenBracket);
+                            }
+                            JsonPathPartialOperator::DeepScanMemberAccess(name) => {
+                                self.check_member_name(&name);
+                                self.operations.push(JsonPathOperator::DeepScanMemberAccess(name));
+                                self.partial_operations.push(JsonPathPartialOperator::OpenBracket);
                             }
-                            _ => self.partial_operations.push(JsonPathPartialOperator::OpenBracket),
+                            JsonPathPartialOperator::FilterExpression { depth, quote, expr } => self.push_filter_char(depth, quote, expr, c),
+                            JsonPathPartialOperator::OpenSingleQuotes(mut name) => {
+                                name.push(c);
+                                self.partial_operations.push(JsonPathPartialOperator::OpenSingleQuotes(name));
+                            }
+                            JsonPathPartialOperator::OpenDoubleQuotes(mut name) => {
+                                name.push(c);
+                                self.partial_operations.push(JsonPathPartialOperator::OpenDoubleQuotes(name));
+                            }
+                            partial_operation => self.set_error(expected(&partial_operation)),
                         }
+                    } else if self.operations.is_empty() {
+                        self.set_error(self.expected_next());
                     } else {
                         self.partial_operations.push(JsonPathPartialOperator::OpenBracket)
                     }
@@ -190,12 +366,7 @@ impl JsonPath {
                 '0' | '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9' => {
                     if let Some(partial_operation) = self.partial_operations.pop() {
                         match partial_operation {
-                            JsonPathPartialOperator::OpenRootBracket => self.partial_operations.push(JsonPathPartialOperator::ArrayRootIndex(String::from(c))),
                             JsonPathPartialOperator::OpenBracket => self.partial_operations.push(JsonPathPartialOperator::ArrayIndex(String::from(c))),
-                            JsonPathPartialOperator::ArrayRootIndex(mut index) => {
-                                index.push(c);
-                                self.partial_operations.push(JsonPathPartialOperator::ArrayRootIndex(index));
-                            }
                             JsonPathPartialOperator::ArrayIndex(mut index) => {
                                 index.push(c);
                                 self.partial_operations.push(JsonPathPartialOperator::ArrayIndex(index));
@@ -204,56 +375,20 @@ impl JsonPath {
                                 index.push(c);
                                 self.partial_operations.push(JsonPathPartialOperator::ArraySlice(index));
                             }
-                            JsonPathPartialOperator::FilterExpression { depth, mut expr } => {
-                                expr.push(c);
-                                self.partial_operations.push(JsonPathPartialOperator::FilterExpression { depth, expr })
-                            }
-                            _ => todo!(),
+                            JsonPathPartialOperator::FilterExpression { depth, quote, expr } => self.push_filter_char(depth, quote, expr, c),
+                            partial_operation => self.set_error(expected(&partial_operation)),
                         }
                     } else {
-                        todo!();
+                        self.set_error(self.expected_next());
                     }
                 }
                 ']' => {
                     if let Some(partial_operation) = self.partial_operations.pop() {
                         match partial_operation {
-                            JsonPathPartialOperator::ArrayRootIndex(index) => {
-                                if let Ok(i) = index.as_str().parse::<isize>() {
-                                    self.operations.push(JsonPathOperator::ArrayRoot(i));
-                                }
-                            }
-                            JsonPathPartialOperator::ArrayIndex(index) => {
-                                if let Ok(i) = index.as_str().parse::<isize>() {
-                                    self.operations.push(JsonPathOperator::ArrayIndex(i));
-                                }
-                            }
-                            JsonPathPartialOperator::ArraySlice(index) => {
-                                let mut indexes = index.split(':');
-                                let first = indexes.next();
-                                let second = indexes.next();
-
-                                if let Some(start) = first {
-                                    if let Some(end) = second {
-                                        if let Ok(start) = start.parse::<isize>() {
-                                            if let Ok(end) = end.parse::<isize>() {
-                                                self.operations.push(JsonPathOperator::ArraySlice(start, end));
-                                            } else {
-                                                panic!("open ended slices not supported yet");
-                                            }
-                                        } else {
-                                            panic!("open ended slices not supported yet");
-                                        }
-                                    } else {
-                                        panic!("open ended slices not supported yet");
-                                    }
-                                } else {
-                                    panic!("open ended slices not supported yet");
-                                }
-                            }
-                            JsonPathPartialOperator::FilterExpression { depth, mut expr } => {
-                                expr.push(c);
-                                self.partial_operations.push(JsonPathPartialOperator::FilterExpression { depth, expr })
-                            }
+                            JsonPathPartialOperator::ArrayIndex(index) => self.push_index(&index, c),
+                            JsonPathPartialOperator::ArraySlice(slice) => self.push_slice(&slice, c),
+                            JsonPathPartialOperator::BracketedWildcard => self.push_selector(JsonPathOperator::Wildcard, c),
+                            JsonPathPartialOperator::FilterExpression { depth, quote, expr } => self.push_filter_char(depth, quote, expr, c),
                             JsonPathPartialOperator::OpenSingleQuotes(mut name) => {
                                 name.push(c);
                                 self.partial_operations.push(JsonPathPartialOperator::OpenSingleQuotes(name));
@@ -262,114 +397,63 @@ impl JsonPath {
                                 name.push(c);
                                 self.partial_operations.push(JsonPathPartialOperator::OpenDoubleQuotes(name));
                             }
-                            JsonPathPartialOperator::ClosedSingleQuotes(name) => {
-                                self.operations.push(JsonPathOperator::MemberAccess(name));
-                            }
-                            JsonPathPartialOperator::ClosedDoubleQuotes(name) => {
-                                self.operations.push(JsonPathOperator::MemberAccess(name));
-                            }
-                            _ => {}
+                            JsonPathPartialOperator::ClosedSingleQuotes(name) | JsonPathPartialOperator::ClosedDoubleQuotes(name) => self.push_selector(JsonPathOperator::MemberAccess(name), c),
+                            partial_operation => self.set_error(expected(&partial_operation)),
                         }
+                    } else {
+                        self.set_error(self.expected_next());
                     }
                 }
                 '\'' => {
                     if let Some(partial_operation) = self.partial_operations.pop() {
                         match partial_operation {
-                            JsonPathPartialOperator::OpenRootBracket => {
-                                self.operations.push(JsonPathOperator::ObjectRoot);
-                                self.partial_operations.push(JsonPathPartialOperator::OpenSingleQuotes(String::new()));
-                            }
                             JsonPathPartialOperator::OpenBracket => {
                                 self.partial_operations.push(JsonPathPartialOperator::OpenSingleQuotes(String::new()));
                             }
-                            JsonPathPartialOperator::FilterExpression { depth, mut expr } => {
-                                expr.push(c);
-                                self.partial_operations.push(JsonPathPartialOperator::FilterExpression { depth, expr })
-                            }
-                            JsonPathPartialOperator::EscapeCharacter() => {
-                                if let Some(partial_operation) = self.partial_operations.pop() {
-                                    match partial_operation {
-                                        JsonPathPartialOperator::OpenSingleQuotes(mut name) => {
-                                            name.push(c);
-                                            self.partial_operations.push(JsonPathPartialOperator::OpenSingleQuotes(name));
-                                        }
-                                        JsonPathPartialOperator::OpenDoubleQuotes(mut name) => {
-                                            name.push(c);
-                                            self.partial_operations.push(JsonPathPartialOperator::OpenDoubleQuotes(name));
-                                        }
-                                        _ => panic!("unexpected character '{}'", c),
-                                    }
-                                }
-                            }
+                            JsonPathPartialOperator::FilterExpression { depth, quote, expr } => self.push_filter_char(depth, quote, expr, c),
                             JsonPathPartialOperator::OpenSingleQuotes(name) => self.partial_operations.push(JsonPathPartialOperator::ClosedSingleQuotes(name)),
                             JsonPathPartialOperator::OpenDoubleQuotes(mut name) => {
                                 name.push(c);
                                 self.partial_operations.push(JsonPathPartialOperator::OpenDoubleQuotes(name));
                             }
-                            _ => todo!(),
+                            partial_operation => self.set_error(expected(&partial_operation)),
                         }
                     } else {
-                        todo!();
+                        self.set_error(self.expected_next());
                     }
                 }
                 '"' => {
                     if let Some(partial_operation) = self.partial_operations.pop() {
                         match partial_operation {
-                            JsonPathPartialOperator::OpenRootBracket => {
-                                self.operations.push(JsonPathOperator::ObjectRoot);
-                                self.partial_operations.push(JsonPathPartialOperator::OpenDoubleQuotes(String::new()));
-                            }
                             JsonPathPartialOperator::OpenBracket => self.partial_operations.push(JsonPathPartialOperator::OpenDoubleQuotes(String::new())),
-                            JsonPathPartialOperator::FilterExpression { depth, mut expr } => {
-                                expr.push(c);
-                                self.partial_operations.push(JsonPathPartialOperator::FilterExpression { depth, expr })
-                            }
-                            JsonPathPartialOperator::EscapeCharacter() => {
-                                if let Some(partial_operation) = self.partial_operations.pop() {
-                                    match partial_operation {
-                                        JsonPathPartialOperator::OpenSingleQuotes(mut name) => {
-                                            name.push(c);
-                                            self.partial_operations.push(JsonPathPartialOperator::OpenSingleQuotes(name));
-                                        }
-                                        JsonPathPartialOperator::OpenDoubleQuotes(mut name) => {
-                                            name.push(c);
-                                            self.partial_operations.push(JsonPathPartialOperator::OpenDoubleQuotes(name));
-                                        }
-                                        _ => panic!("unexpected character '{}'", c),
-                                    }
-                                }
-                            }
+                            JsonPathPartialOperator::FilterExpression { depth, quote, expr } => self.push_filter_char(depth, quote, expr, c),
                             JsonPathPartialOperator::OpenSingleQuotes(mut name) => {
                                 name.push(c);
                                 self.partial_operations.push(JsonPathPartialOperator::OpenSingleQuotes(name))
                             }
                             JsonPathPartialOperator::OpenDoubleQuotes(name) => self.partial_operations.push(JsonPathPartialOperator::ClosedDoubleQuotes(name)),
-                            _ => todo!(),
+                            partial_operation => self.set_error(expected(&partial_operation)),
                         }
                     } else {
-                        todo!();
+                        self.set_error(self.expected_next());
                     }
                 }
                 '\\' => {
                     if let Some(partial_operation) = self.partial_operations.pop() {
                         match partial_operation {
-                            JsonPathPartialOperator::FilterExpression { depth, mut expr } => {
-                                expr.push(c);
-                                self.partial_operations.push(JsonPathPartialOperator::FilterExpression { depth, expr })
-                            }
-                            JsonPathPartialOperator::EscapeCharacter() => {}
+                            JsonPathPartialOperator::FilterExpression { depth, quote, expr } => self.push_filter_char(depth, quote, expr, c),
                             JsonPathPartialOperator::OpenSingleQuotes(name) => {
                                 self.partial_operations.push(JsonPathPartialOperator::OpenSingleQuotes(name));
-                                self.partial_operations.push(JsonPathPartialOperator::EscapeCharacter());
+                                self.partial_operations.push(JsonPathPartialOperator::EscapeCharacter(String::new()));
                             }
                             JsonPathPartialOperator::OpenDoubleQuotes(name) => {
                                 self.partial_operations.push(JsonPathPartialOperator::OpenDoubleQuotes(name));
-                                self.partial_operations.push(JsonPathPartialOperator::EscapeCharacter())
+                                self.partial_operations.push(JsonPathPartialOperator::EscapeCharacter(String::new()))
                             }
-                            _ => todo!(),
+                            partial_operation => self.set_error(expected(&partial_operation)),
                         }
                     } else {
-                        todo!();
+                        self.set_error(self.expected_next());
                     }
                 }
                 ':' => {
@@ -384,21 +468,16 @@ impl JsonPath {
                                 name.push(c);
                                 self.partial_operations.push(JsonPathPartialOperator::DeepScanMemberAccess(name));
                             }
-                            JsonPathPartialOperator::Root => todo!("{}", c),
-                            JsonPathPartialOperator::OpenRootBracket => todo!("{}", c),
-                            JsonPathPartialOperator::ArrayRootIndex(_) => todo!("{}", c),
                             JsonPathPartialOperator::OpenBracket => self.partial_operations.push(JsonPathPartialOperator::ArraySlice(String::from(c))),
                             JsonPathPartialOperator::ArrayIndex(mut index) => {
                                 index.push(c);
                                 self.partial_operations.push(JsonPathPartialOperator::ArraySlice(index));
                             }
-                            JsonPathPartialOperator::ArraySlice(_) => todo!("{}", c),
-                            JsonPathPartialOperator::OpenFilter => todo!("{}", c),
-                            JsonPathPartialOperator::FilterExpression { depth, mut expr } => {
-                                expr.push(c);
-                                self.partial_operations.push(JsonPathPartialOperator::FilterExpression { depth, expr })
+                            JsonPathPartialOperator::ArraySlice(mut slice) => {
+                                slice.push(c);
+                                self.partial_operations.push(JsonPathPartialOperator::ArraySlice(slice));
                             }
-                            JsonPathPartialOperator::EscapeCharacter() => todo!("{}", c),
+                            JsonPathPartialOperator::FilterExpression { depth, quote, expr } => self.push_filter_char(depth, quote, expr, c),
                             JsonPathPartialOperator::OpenSingleQuotes(mut name) => {
                                 name.push(c);
                                 self.partial_operations.push(JsonPathPartialOperator::OpenSingleQuotes(name));
@@ -407,33 +486,31 @@ impl JsonPath {
                                 name.push(c);
                                 self.partial_operations.push(JsonPathPartialOperator::OpenDoubleQuotes(name));
                             }
-                            JsonPathPartialOperator::ClosedSingleQuotes(_) => todo!("{}", c),
-                            JsonPathPartialOperator::ClosedDoubleQuotes(_) => todo!("{}", c),
+                            partial_operation => self.set_error(expected(&partial_operation)),
                         }
                     } else {
-                        todo!("{}", c);
+                        self.set_error(self.expected_next());
                     }
                 }
-                '?' => {
+                '-' => {
                     if let Some(partial_operation) = self.partial_operations.pop() {
                         match partial_operation {
-                            JsonPathPartialOperator::Root => todo!(),
-                            JsonPathPartialOperator::OpenRootBracket => todo!(),
-                            JsonPathPartialOperator::ArrayRootIndex(_) => todo!(),
-                            JsonPathPartialOperator::PreMemberAccess => todo!(),
-                            JsonPathPartialOperator::MemberAccess(_) => todo!(),
-                            JsonPathPartialOperator::DeepScanMemberAccess(_) => todo!(),
-                            JsonPathPartialOperator::OpenBracket => {
-                                self.partial_operations.push(JsonPathPartialOperator::OpenFilter);
+                            // A negative number could be an index or the start of a slice, which isn't known until either `]` or `:`.
+                            JsonPathPartialOperator::OpenBracket => self.partial_operations.push(JsonPathPartialOperator::ArrayIndex(String::from(c))),
+                            JsonPathPartialOperator::ArraySlice(mut slice) => {
+                                slice.push(c);
+                                self.partial_operations.push(JsonPathPartialOperator::ArraySlice(slice));
                             }
-                            JsonPathPartialOperator::ArrayIndex(_) => todo!(),
-                            JsonPathPartialOperator::ArraySlice(_) => todo!(),
-                            JsonPathPartialOperator::OpenFilter => todo!("{}", c),
-                            JsonPathPartialOperator::FilterExpression { depth, mut expr } => {
-                                expr.push(c);
-                                self.partial_operations.push(JsonPathPartialOperator::FilterExpression { depth, expr })
+                            JsonPathPartialOperator::PreMemberAccess => self.partial_operations.push(JsonPathPartialOperator::MemberAccess(String::from(c))),
+                            JsonPathPartialOperator::MemberAccess(mut name) => {
+                                name.push(c);
+                                self.partial_operations.push(JsonPathPartialOperator::MemberAccess(name));
+                            }
+                            JsonPathPartialOperator::DeepScanMemberAccess(mut name) => {
+                                name.push(c);
+                                self.partial_operations.push(JsonPathPartialOperator::DeepScanMemberAccess(name));
                             }
-                            JsonPathPartialOperator::EscapeCharacter() => todo!(),
+                            JsonPathPartialOperator::FilterExpression { depth, quote, expr } => self.push_filter_char(depth, quote, expr, c),
                             JsonPathPartialOperator::OpenSingleQuotes(mut name) => {
                                 name.push(c);
                                 self.partial_operations.push(JsonPathPartialOperator::OpenSingleQuotes(name));
@@ -442,34 +519,82 @@ impl JsonPath {
                                 name.push(c);
                                 self.partial_operations.push(JsonPathPartialOperator::OpenDoubleQuotes(name));
                             }
-                            JsonPathPartialOperator::ClosedSingleQuotes(_) => todo!(),
-                            JsonPathPartialOperator::ClosedDoubleQuotes(_) => todo!(),
+                            partial_operation => self.set_error(expected(&partial_operation)),
                         }
                     } else {
-                        todo!("{}", c);
+                        self.set_error(self.expected_next());
+                    }
+                }
+                ',' => {
+                    if let Some(partial_operation) = self.partial_operations.pop() {
+                        match partial_operation {
+                            JsonPathPartialOperator::ArrayIndex(index) => self.push_index(&index, c),
+                            JsonPathPartialOperator::ArraySlice(slice) => self.push_slice(&slice, c),
+                            JsonPathPartialOperator::BracketedWildcard => self.push_selector(JsonPathOperator::Wildcard, c),
+                            JsonPathPartialOperator::ClosedSingleQuotes(name) | JsonPathPartialOperator::ClosedDoubleQuotes(name) => self.push_selector(JsonPathOperator::MemberAccess(name), c),
+                            JsonPathPartialOperator::FilterExpression { depth, quote, expr } => self.push_filter_char(depth, quote, expr, c),
+                            JsonPathPartialOperator::OpenSingleQuotes(mut name) => {
+                                name.push(c);
+                                self.partial_operations.push(JsonPathPartialOperator::OpenSingleQuotes(name));
+                            }
+                            JsonPathPartialOperator::OpenDoubleQuotes(mut name) => {
+                                name.push(c);
+                                self.partial_operations.push(JsonPathPartialOperator::OpenDoubleQuotes(name));
+                            }
+                            partial_operation => self.set_error(expected(&partial_operation)),
+                        }
+                    } else {
+                        self.set_error(self.expected_next());
+                    }
+                }
+                '*' => {
+                    if let Some(partial_operation) = self.partial_operations.pop() {
+                        match partial_operation {
+                            JsonPathPartialOperator::PreMemberAccess => self.operations.push(JsonPathOperator::Wildcard),
+                            JsonPathPartialOperator::DeepScanMemberAccess(name) if name.is_empty() => self.operations.push(JsonPathOperator::DeepScanWildcard),
+                            JsonPathPartialOperator::OpenBracket => self.partial_operations.push(JsonPathPartialOperator::BracketedWildcard),
+                            JsonPathPartialOperator::FilterExpression { depth, quote, expr } => self.push_filter_char(depth, quote, expr, c),
+                            JsonPathPartialOperator::OpenSingleQuotes(mut name) => {
+                                name.push(c);
+                                self.partial_operations.push(JsonPathPartialOperator::OpenSingleQuotes(name));
+                            }
+                            JsonPathPartialOperator::OpenDoubleQuotes(mut name) => {
+                                name.push(c);
+                                self.partial_operations.push(JsonPathPartialOperator::OpenDoubleQuotes(name));
+                            }
+                            partial_operation => self.set_error(expected(&partial_operation)),
+                        }
+                    } else {
+                        self.set_error(self.expected_next());
+                    }
+                }
+                '?' => {
+                    if let Some(partial_operation) = self.partial_operations.pop() {
+                        match partial_operation {
+                            JsonPathPartialOperator::OpenBracket => self.partial_operations.push(JsonPathPartialOperator::FilterExpression {
+                                depth: 0,
+                                quote: None,
+                                expr: String::new(),
+                            }),
+                            JsonPathPartialOperator::FilterExpression { depth, quote, expr } => self.push_filter_char(depth, quote, expr, c),
+                            JsonPathPartialOperator::OpenSingleQuotes(mut name) => {
+                                name.push(c);
+                                self.partial_operations.push(JsonPathPartialOperator::OpenSingleQuotes(name));
+                            }
+                            JsonPathPartialOperator::OpenDoubleQuotes(mut name) => {
+                                name.push(c);
+                                self.partial_operations.push(JsonPathPartialOperator::OpenDoubleQuotes(name));
+                            }
+                            partial_operation => self.set_error(expected(&partial_operation)),
+                        }
+                    } else {
+                        self.set_error(self.expected_next());
                     }
                 }
                 '(' => {
                     if let Some(partial_operation) = self.partial_operations.pop() {
                         match partial_operation {
-                            JsonPathPartialOperator::Root => todo!(),
-                            JsonPathPartialOperator::OpenRootBracket => todo!(),
-                            JsonPathPartialOperator::ArrayRootIndex(_) => todo!(),
-                            JsonPathPartialOperator::PreMemberAccess => todo!(),
-                            JsonPathPartialOperator::MemberAccess(_) => todo!(),
-                            JsonPathPartialOperator::DeepScanMemberAccess(_) => todo!(),
-                            JsonPathPartialOperator::OpenBracket => todo!(),
-                            JsonPathPartialOperator::ArrayIndex(_) => todo!(),
-                            JsonPathPartialOperator::ArraySlice(_) => todo!(),
-                            JsonPathPartialOperator::OpenFilter => {
-                                self.partial_operations.push(JsonPathPartialOperator::FilterExpression { depth: 0, expr: String::new() });
-                            }
-                            JsonPathPartialOperator::FilterExpression { mut depth, mut expr } => {
-                                expr.push(c);
-                                depth += 1;
-                                self.partial_operations.push(JsonPathPartialOperator::FilterExpression { depth, expr })
-                            }
-                            JsonPathPartialOperator::EscapeCharacter() => todo!(),
+                            JsonPathPartialOperator::FilterExpression { depth, quote, expr } => self.push_filter_char(depth, quote, expr, c),
                             JsonPathPartialOperator::OpenSingleQuotes(mut name) => {
                                 name.push(c);
                                 self.partial_operations.push(JsonPathPartialOperator::OpenSingleQuotes(name));
@@ -478,36 +603,16 @@ impl JsonPath {
                                 name.push(c);
                                 self.partial_operations.push(JsonPathPartialOperator::OpenDoubleQuotes(name));
                             }
-                            JsonPathPartialOperator::ClosedSingleQuotes(_) => todo!(),
-                            JsonPathPartialOperator::ClosedDoubleQuotes(_) => todo!(),
+                            partial_operation => self.set_error(expected(&partial_operation)),
                         }
                     } else {
-                        todo!("{}", c);
+                        self.set_error(self.expected_next());
                     }
                 }
                 ')' => {
                     if let Some(partial_operation) = self.partial_operations.pop() {
                         match partial_operation {
-                            JsonPathPartialOperator::Root => todo!(),
-                            JsonPathPartialOperator::OpenRootBracket => todo!(),
-                            JsonPathPartialOperator::ArrayRootIndex(_) => todo!(),
-                            JsonPathPartialOperator::PreMemberAccess => todo!(),
-                            JsonPathPartialOperator::MemberAccess(_) => todo!(),
-                            JsonPathPartialOperator::DeepScanMemberAccess(_) => todo!(),
-                            JsonPathPartialOperator::OpenBracket => todo!(),
-                            JsonPathPartialOperator::ArrayIndex(_) => todo!(),
-                            JsonPathPartialOperator::ArraySlice(_) => todo!(),
-                            JsonPathPartialOperator::OpenFilter => todo!(),
-                            JsonPathPartialOperator::FilterExpression { mut depth, mut expr } => {
-                                if depth > 0 {
-                                    expr.push(c);
-                                    depth -= 1;
-                                    self.partial_operations.push(JsonPathPartialOperator::FilterExpression { depth, expr });
-                                } else {
-                                    self.operations.push(JsonPathOperator::FilterExpression(expr));
-                                }
-                            }
-                            JsonPathPartialOperator::EscapeCharacter() => todo!(),
+                            JsonPathPartialOperator::FilterExpression { depth, quote, expr } => self.push_filter_char(depth, quote, expr, c),
                             JsonPathPartialOperator::OpenSingleQuotes(mut name) => {
                                 name.push(c);
                                 self.partial_operations.push(JsonPathPartialOperator::OpenSingleQuotes(name));
@@ -516,46 +621,53 @@ impl JsonPath {
                                 name.push(c);
                                 self.partial_operations.push(JsonPathPartialOperator::OpenDoubleQuotes(name));
                             }
-                            JsonPathPartialOperator::ClosedSingleQuotes(_) => todo!(),
-                            JsonPathPartialOperator::ClosedDoubleQuotes(_) => todo!(),
+                            partial_operation => self.set_error(expected(&partial_operation)),
                         }
+                    } else {
+                        self.set_error(self.expected_next());
                     }
                 }
                 '\n' => {
                     if let Some(partial_operation) = self.partial_operations.pop() {
                         match partial_operation {
-                            JsonPathPartialOperator::Root => self.operations.push(JsonPathOperator::ObjectRoot),
-                            JsonPathPartialOperator::OpenRootBracket => self.operations.push(JsonPathOperator::ArrayRoot(-1)),
-                            JsonPathPartialOperator::ArrayRootIndex(index) => {
-                                if let Ok(index) = index.as_str().parse::<isize>() {
-                                    self.operations.push(JsonPathOperator::ArrayRoot(index));
-                                }
-                            }
-                            JsonPathPartialOperator::PreMemberAccess => {}
-                            JsonPathPartialOperator::MemberAccess(name) => self.operations.push(JsonPathOperator::MemberAccess(name)),
-                            JsonPathPartialOperator::DeepScanMemberAccess(name) => self.operations.push(JsonPathOperator::DeepScanMemberAccess(name)),
-                            JsonPathPartialOperator::OpenBracket => self.operations.push(JsonPathOperator::MemberAccess(String::new())),
-                            JsonPathPartialOperator::ArrayIndex(index) => {
-                                if let Ok(index) = index.as_str().parse::<isize>() {
-                                    self.operations.push(JsonPathOperator::ArrayIndex(index));
-                                }
-                            }
-                            JsonPathPartialOperator::ArraySlice(_) => todo!(),
-                            JsonPathPartialOperator::OpenFilter => todo!(),
-                            JsonPathPartialOperator::FilterExpression { depth: _, expr: _ } => {
-                                todo!()
-                            }
-                            JsonPathPartialOperator::EscapeCharacter() => todo!(),
-                            JsonPathPartialOperator::OpenSingleQuotes(_) => todo!(),
-                            JsonPathPartialOperator::OpenDoubleQuotes(_) => todo!(),
-                            JsonPathPartialOperator::ClosedSingleQuotes(_) => todo!(),
-                            JsonPathPartialOperator::ClosedDoubleQuotes(_) => todo!(),
+                            JsonPathPartialOperator::Root => self.operations.push(JsonPathOperator::Root),
+                            JsonPathPartialOperator::PreMemberAccess => self.set_non_standard("a '.' at the end of the path"),
+                            JsonPathPartialOperator::DeepScanMemberAccess(name) if name.is_empty() => self.set_error(expected(&JsonPathPartialOperator::DeepScanMemberAccess(name))),
+                            JsonPathPartialOperator::MemberAccess(name) => {
+                                self.check_member_name(&name);
+                                self.operations.push(JsonPathOperator::MemberAccess(name));
+                            }
+                            JsonPathPartialOperator::DeepScanMemberAccess(name) => {
+                                self.check_member_name(&name);
+                                self.operations.push(JsonPathOperator::DeepScanMemberAccess(name));
+                            }
+                            JsonPathPartialOperator::FilterExpression { depth, quote, expr } => self.push_filter_char(depth, quote, expr, c),
+                            partial_operation => self.set_error(expected(&partial_operation)),
                         }
+                    } else if self.operations.is_empty() {
+                        self.set_error(self.expected_next());
                     }
                 }
                 _ => {
                     if let Some(partial_operation) = self.partial_operations.pop() {
                         match partial_operation {
+                            // Selectors in brackets can have whitespace around them, e.g. `[0, 2]`.
+                            JsonPathPartialOperator::OpenBracket
+                            | JsonPathPartialOperator::BracketedWildcard
+                            | JsonPathPartialOperator::ClosedSingleQuotes(_)
+                            | JsonPathPartialOperator::ClosedDoubleQuotes(_)
+                                if c.is_whitespace() =>
+                            {
+                                self.partial_operations.push(partial_operation)
+                            }
+                            JsonPathPartialOperator::ArrayIndex(mut index) if c.is_whitespace() => {
+                                index.push(c);
+                                self.partial_operations.push(JsonPathPartialOperator::ArrayIndex(index));
+                            }
+                            JsonPathPartialOperator::ArraySlice(mut slice) if c.is_whitespace() => {
+                                slice.push(c);
+                                self.partial_operations.push(JsonPathPartialOperator::ArraySlice(slice));
+                            }
                             JsonPathPartialOperator::PreMemberAccess => self.partial_operations.push(JsonPathPartialOperator::MemberAccess(String::from(c))),
                             JsonPathPartialOperator::MemberAccess(mut name) => {
                                 name.push(c);
@@ -565,18 +677,7 @@ impl JsonPath {
                                 name.push(c);
                                 self.partial_operations.push(JsonPathPartialOperator::DeepScanMemberAccess(name));
                             }
-                            JsonPathPartialOperator::Root => todo!("{}", c),
-                            JsonPathPartialOperator::OpenRootBracket => todo!("{}", c),
-                            JsonPathPartialOperator::ArrayRootIndex(_) => todo!("{}", c),
-                            JsonPathPartialOperator::OpenBracket => todo!("{}", c),
-                            JsonPathPartialOperator::ArrayIndex(_) => todo!("{}", c),
-                            JsonPathPartialOperator::ArraySlice(_) => todo!("{}", c),
-                            JsonPathPartialOperator::OpenFilter => todo!("{}", c),
-                            JsonPathPartialOperator::FilterExpression { depth, mut expr } => {
-                                expr.push(c);
-                                self.partial_operations.push(JsonPathPartialOperator::FilterExpression { depth, expr })
-                            }
-                            JsonPathPartialOperator::EscapeCharacter() => todo!("{}", c),
+                            JsonPathPartialOperator::FilterExpression { depth, quote, expr } => self.push_filter_char(depth, quote, expr, c),
                             JsonPathPartialOperator::OpenSingleQuotes(mut name) => {
                                 name.push(c);
                                 self.partial_operations.push(JsonPathPartialOperator::OpenSingleQuotes(name));
@@ -585,15 +686,296 @@ impl JsonPath {
                                 name.push(c);
                                 self.partial_operations.push(JsonPathPartialOperator::OpenDoubleQuotes(name));
                             }
-                            JsonPathPartialOperator::ClosedSingleQuotes(_) => todo!("{}", c),
-                            JsonPathPartialOperator::ClosedDoubleQuotes(_) => todo!("{}", c),
+                            partial_operation => self.set_error(expected(&partial_operation)),
                         }
                     } else {
-                        todo!("[{}]", c);
+                        self.set_error(self.expected_next());
+                    }
+                }
+            }
+        }
+    }
+
+    /// Describes what can come next when there's nothing on the stack, which is either the start of the path or just after
+    /// a segment that is already complete such as `.*` or `[0]`.
+    fn expected_next(&self) -> &'static str {
+        match self.operations.is_empty() {
+            true => "'$'",
+            false => "'.', '[' or the end of the path",
+        }
+    }
+
+    fn set_error(&mut self, expected: &str) {
+        self.set_error_at(self.position, expected, self.found_at(self.position));
+    }
+
+    fn set_error_at(&mut self, position: usize, expected: &str, found: String) {
+        if self.error.is_none() {
+            self.error = Some(JsonPathError {
+                path: self.path.clone(),
+                position,
+                expected: String::from(expected),
+                found,
+            });
+        }
+    }
+
+    fn found_at(&self, position: usize) -> String {
+        match self.path.chars().nth(position) {
+            Some(c) => format!("'{}'", c.escape_debug()),
+            None => String::from("the end of the path"),
+        }
+    }
+
+    /// Adds an index from between brackets. The error points at the start of the index rather than the `]` after it.
+    fn push_index(&mut self, index: &str, c: char) {
+        self.check_integer(index.trim());
+
+        match parse_index(index) {
+            Some(selector) => self.push_selector(selector, c),
+            None => self.set_error_at(self.position - index.chars().count(), "an array index", format!("'{}'", index.trim())),
+        }
+    }
+
+    fn push_slice(&mut self, slice: &str, c: char) {
+        for bound in slice.split(':') {
+            self.check_integer(bound.trim());
+        }
+
+        match parse_slice(slice) {
+            Some(selector) => self.push_selector(selector, c),
+            None => self.set_error_at(self.position - slice.chars().count(), "an array slice such as '1:3' or '::2'", format!("'{}'", slice.trim())),
+        }
+    }
+
+    /// Adds a character to a filter expression, which carries on until the `]` that closes the brackets it was opened in.
+    /// Brackets and parentheses inside the expression, and anything in quotes, are skipped over.
+    fn push_filter_char(&mut self, mut depth: isize, mut quote: Option<char>, mut expr: String, c: char) {
+        match (quote, c) {
+            (_, '\n') => {
+                self.set_error(expected(&JsonPathPartialOperator::FilterExpression { depth, quote, expr }));
+                return;
+            }
+            (Some(_), _) if is_escaped(&expr) => {}
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '(' | '[') => depth += 1,
+            (None, ']' | ',') if depth == 0 => {
+                match JsonFilter::parse(&expr) {
+                    Ok(filter) => self.push_selector(JsonPathOperator::FilterExpression(filter), c),
+                    Err(err) => {
+                        let position = self.position - expr.chars().count() + err.position;
+                        self.set_error_at(position, &err.expected, self.found_at(position));
                     }
                 }
+
+                return;
             }
+            (None, ')' | ']') => depth -= 1,
+            (None, _) => {}
         }
+
+        expr.push(c);
+        self.partial_operations.push(JsonPathPartialOperator::FilterExpression { depth, quote, expr });
+    }
+
+    /// Decodes an escape sequence in a quoted member name, with `escape` holding what has been read of it after the `\\`.
+    /// These are the same as in JSON strings, along with `\\'` in single quotes. A `\\u` escape for a high surrogate needs
+    /// to be followed by one for a low surrogate so the pair can be decoded into a single character.
+    fn push_escape_char(&mut self, mut escape: String, c: char) {
+        escape.push(c);
+
+        let is_single_quoted = matches!(self.partial_operations.last(), Some(JsonPathPartialOperator::OpenSingleQuotes(_)));
+        let code_unit = |start: usize| u32::from_str_radix(&escape[start..start + 4], 16).unwrap_or_default();
+
+        let decoded = match (escape.len(), c) {
+            (1, 'b') => '\u{8}',
+            (1, 'f') => '\u{c}',
+            (1, 'n') => '\n',
+            (1, 'r') => '\r',
+            (1, 't') => '\t',
+            (1, '/' | '\\') => c,
+            (1, '\'' | '"') => {
+                if is_single_quoted == (c == '"') {
+                    self.set_non_standard(format!("'\\{}' inside {} quotes", c, if is_single_quoted { "single" } else { "double" }).as_str());
+                }
+
+                c
+            }
+            (1, 'u') | (6, '\\') | (7, 'u') => return self.partial_operations.push(JsonPathPartialOperator::EscapeCharacter(escape)),
+            (2..=4 | 8..=10, _) if c.is_ascii_hexdigit() => return self.partial_operations.push(JsonPathPartialOperator::EscapeCharacter(escape)),
+            (5, _) if c.is_ascii_hexdigit() => match code_unit(1) {
+                0xd800..=0xdbff => return self.partial_operations.push(JsonPathPartialOperator::EscapeCharacter(escape)),
+                0xdc00..=0xdfff => return self.set_error("a high surrogate before the low surrogate"),
+                unit => char::from_u32(unit).unwrap_or(char::REPLACEMENT_CHARACTER),
+            },
+            (11, _) if c.is_ascii_hexdigit() => match (code_unit(1), code_unit(7)) {
+                (high, low @ 0xdc00..=0xdfff) => char::from_u32(0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)).unwrap_or(char::REPLACEMENT_CHARACTER),
+                _ => return self.set_error("a low surrogate after the high surrogate"),
+            },
+            (1, _) => return self.set_error("an escape character such as 'n' or 'u'"),
+            (6 | 7, _) => return self.set_error("a '\\u' escape for the low surrogate after the high surrogate"),
+            _ => return self.set_error("a hexadecimal digit"),
+        };
+
+        match self.partial_operations.pop() {
+            Some(JsonPathPartialOperator::OpenSingleQuotes(mut name)) => {
+                name.push(decoded);
+                self.partial_operations.push(JsonPathPartialOperator::OpenSingleQuotes(name));
+            }
+            Some(JsonPathPartialOperator::OpenDoubleQuotes(mut name)) => {
+                name.push(decoded);
+                self.partial_operations.push(JsonPathPartialOperator::OpenDoubleQuotes(name));
+            }
+            _ => self.set_error(self.expected_next()),
+        }
+    }
+
+    /// Checks a member name given without quotes, which RFC 9535 only allows to start with a letter, `_` or a non-ASCII
+    /// character and to carry on with those or digits. Anything else is accepted but isn't standard, e.g. `$.null-test`.
+    fn check_member_name(&mut self, name: &str) {
+        let is_name_char = |c: char| c.is_ascii_alphabetic() || c == '_' || !c.is_ascii();
+
+        match name.chars().next() {
+            None => self.set_non_standard("a '.' without a member name after it"),
+            Some(c) if !is_name_char(c) => self.set_non_standard(format!("'{}' at the start of the member name '{}'", c, name).as_str()),
+            Some(_) => {
+                if let Some(c) = name.chars().find(|c| !is_name_char(*c) && !c.is_ascii_digit()) {
+                    self.set_non_standard(format!("'{}' in the member name '{}' without quotes", c, name).as_str());
+                }
+            }
+        }
+    }
+
+    /// Checks an index or slice bound. RFC 9535 doesn't allow leading zeros, as in `01`, or a negative zero.
+    fn check_integer(&mut self, integer: &str) {
+        let digits = integer.strip_prefix('-').unwrap_or(integer);
+
+        if digits.starts_with('0') && (digits.len() > 1 || digits.len() < integer.len()) {
+            self.set_non_standard(format!("the integer '{}' with a leading zero", integer).as_str());
+        }
+    }
+
+    /// Only the first non-standard part of the path is kept.
+    fn set_non_standard(&mut self, description: &str) {
+        if self.non_standard.is_none() {
+            self.non_standard = Some(String::from(description));
+        }
+    }
+
+    /// Adds a selector from between brackets once `]` or `,` is reached. Selectors separated by commas are gathered into a
+    /// union, and the selector is a deep scan if the brackets came straight after `..`.
+    fn push_selector(&mut self, selector: JsonPathOperator, c: char) {
+        let mut selectors = match self.partial_operations.last() {
+            Some(JsonPathPartialOperator::Union(_)) => match self.partial_operations.pop() {
+                Some(JsonPathPartialOperator::Union(selectors)) => selectors,
+                _ => Vec::new(),
+            },
+            _ => Vec::new(),
+        };
+
+        selectors.push(selector);
+
+        if c == ',' {
+            self.partial_operations.push(JsonPathPartialOperator::Union(selectors));
+            self.partial_operations.push(JsonPathPartialOperator::OpenBracket);
+            return;
+        }
+
+        let selector = match selectors.len() {
+            1 => selectors.remove(0),
+            _ => JsonPathOperator::Union(selectors),
+        };
+
+        match self.partial_operations.last() {
+            Some(JsonPathPartialOperator::DeepScanMemberAccess(scan)) if scan.is_empty() => {
+                self.partial_operations.pop();
+
+                match selector {
+                    JsonPathOperator::MemberAccess(name) => self.operations.push(JsonPathOperator::DeepScanMemberAccess(name)),
+                    JsonPathOperator::Wildcard => self.operations.push(JsonPathOperator::DeepScanWildcard),
+                    selector => self.operations.push(JsonPathOperator::DeepScan(Box::new(selector))),
+                }
+            }
+            _ => self.operations.push(selector),
+        }
+    }
+}
+
+/// Checks whether the next character is escaped, which it is if there are an odd number of backslashes before it.
+fn is_escaped(expr: &str) -> bool {
+    expr.chars().rev().take_while(|c| *c == '\\').count() % 2 == 1
+}
+
+/// Turns `~1` back into `/` and `~0` back into `~` in a JSON pointer token. Any other `~` is an error, given as its
+/// position in the token.
+fn unescape_pointer_token(token: &str) -> Result<String, usize> {
+    let mut name = String::new();
+    let mut chars = token.chars().enumerate();
+
+    while let Some((i, c)) = chars.next() {
+        if c != '~' {
+            name.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some((_, '0')) => name.push('~'),
+            Some((_, '1')) => name.push('/'),
+            _ => return Err(i),
+        }
+    }
+
+    Ok(name)
+}
+
+/// A JSON pointer token is an array index if it's `0` or digits without a leading zero.
+fn parse_pointer_index(token: &str) -> Option<isize> {
+    if token.is_empty() || !token.chars().all(|c| c.is_ascii_digit()) || (token.len() > 1 && token.starts_with('0')) {
+        return None;
+    }
+
+    token.parse().ok()
+}
+
+fn parse_index(index: &str) -> Option<JsonPathOperator> {
+    index.trim().parse::<isize>().ok().map(JsonPathOperator::ArrayIndex)
+}
+
+/// Parses the `start:end:step` between the brackets of a slice, any part of which can be left out.
+fn parse_slice(slice: &str) -> Option<JsonPathOperator> {
+    let bounds: Vec<Option<isize>> = slice
+        .split(':')
+        .map(|bound| match bound.trim() {
+            "" => Some(None),
+            bound => bound.parse::<isize>().ok().map(Some),
+        })
+        .collect::<Option<_>>()?;
+
+    match bounds[..] {
+        [start, end] => Some(JsonPathOperator::ArraySlice(start, end, None)),
+        [start, end, step] => Some(JsonPathOperator::ArraySlice(start, end, step)),
+        _ => None,
+    }
+}
+
+/// Describes what can come next when the given partial operator is on the top of the stack.
+fn expected(partial_operation: &JsonPathPartialOperator) -> &'static str {
+    match partial_operation {
+        JsonPathPartialOperator::Root => "'.', '[' or the end of the path",
+        JsonPathPartialOperator::PreMemberAccess => "a member name, '*', '.' or '['",
+        JsonPathPartialOperator::MemberAccess(_) => "'.', '[' or the end of the path",
+        JsonPathPartialOperator::DeepScanMemberAccess(name) if name.is_empty() => "a member name, '*' or '['",
+        JsonPathPartialOperator::DeepScanMemberAccess(_) => "'.', '[' or the end of the path",
+        JsonPathPartialOperator::OpenBracket => "an index, a slice, a quoted name, '*' or a filter",
+        JsonPathPartialOperator::ArrayIndex(_) | JsonPathPartialOperator::ArraySlice(_) => "a digit, ':', ',' or ']'",
+        JsonPathPartialOperator::FilterExpression { depth: _, quote: _, expr: _ } => "a ']' to close the filter",
+        JsonPathPartialOperator::EscapeCharacter(_) => "an escape character such as 'n' or 'u'",
+        JsonPathPartialOperator::OpenSingleQuotes(_) => "a closing \"'\"",
+        JsonPathPartialOperator::OpenDoubleQuotes(_) => "a closing '\"'",
+        JsonPathPartialOperator::ClosedSingleQuotes(_) | JsonPathPartialOperator::ClosedDoubleQuotes(_) | JsonPathPartialOperator::BracketedWildcard => "',' or ']'",
+        JsonPathPartialOperator::Union(_) => "a selector",
     }
 }
 
@@ -622,54 +1004,69 @@ mod tests {
 
     #[test]
     fn test_json_path_object_root() {
-        assert_eq!(JsonPath::from("$.").to_string(), "ObjectRoot");
+        assert_eq!(JsonPath::from("$.").to_string(), "Root");
     }
 
     #[test]
     fn test_json_path_array_root() {
-        assert_eq!(JsonPath::from("$[5]").to_string(), "ArrayRoot(5)");
+        assert_eq!(JsonPath::from("$[5]").to_string(), "Root -> ArrayIndex(5)");
     }
 
     #[test]
     fn test_json_path_array_root_member_array() {
-        assert_eq!(JsonPath::from("$[10].batters[531]").to_string(), "ArrayRoot(10) -> MemberAccess(batters) -> ArrayIndex(531)");
+        assert_eq!(JsonPath::from("$[10].batters[531]").to_string(), "Root -> ArrayIndex(10) -> MemberAccess(batters) -> ArrayIndex(531)");
     }
 
     #[test]
     fn test_json_path_array_root_bracketed_member_access() {
-        assert_eq!(JsonPath::from("$[10][531]['batters']").to_string(), "ArrayRoot(10) -> ArrayIndex(531) -> MemberAccess(batters)");
+        assert_eq!(
+            JsonPath::from("$[10][531]['batters']").to_string(),
+            "Root -> ArrayIndex(10) -> ArrayIndex(531) -> MemberAccess(batters)"
+        );
     }
 
     #[test]
     fn test_json_path_object_root_member_access() {
-        assert_eq!(JsonPath::from("$.batters").to_string(), "ObjectRoot -> MemberAccess(batters)");
+        assert_eq!(JsonPath::from("$.batters").to_string(), "Root -> MemberAccess(batters)");
     }
 
     #[test]
     fn test_json_path_object_root_member_access_of_member_access() {
-        assert_eq!(JsonPath::from("$.batters.batter").to_string(), "ObjectRoot -> MemberAccess(batters) -> MemberAccess(batter)");
+        assert_eq!(JsonPath::from("$.batters.batter").to_string(), "Root -> MemberAccess(batters) -> MemberAccess(batter)");
     }
 
     #[test]
     fn test_json_path_object_root_deep_scan_member_access() {
-        assert_eq!(JsonPath::from("$..batter").to_string(), "ObjectRoot -> DeepScanMemberAccess(batter)");
+        assert_eq!(JsonPath::from("$..batter").to_string(), "Root -> DeepScanMemberAccess(batter)");
+    }
+
+    #[test]
+    fn test_json_path_object_root_bracketed_deep_scan_member_access() {
+        assert_eq!(
+            JsonPath::from("$..['batter'][1].type").to_string(),
+            "Root -> DeepScanMemberAccess(batter) -> ArrayIndex(1) -> MemberAccess(type)"
+        );
+        assert_eq!(JsonPath::from("$..batter..type").to_string(), "Root -> DeepScanMemberAccess(batter) -> DeepScanMemberAccess(type)");
+        assert_eq!(JsonPath::from("$..[0]").to_string(), "Root -> DeepScan(ArrayIndex(0))");
+        assert_eq!(JsonPath::from("$..[0,'a'].b").to_string(), "Root -> DeepScan(Union(ArrayIndex(0),MemberAccess(a))) -> MemberAccess(b)");
+        assert_eq!(JsonPath::from("$..[?(@.a)]").to_string(), "Root -> DeepScan(FilterExpression(@.a))");
     }
 
     #[test]
     fn test_json_path_object_root_array_index_of_member_access() {
-        assert_eq!(JsonPath::from("$.batters[252]").to_string(), "ObjectRoot -> MemberAccess(batters) -> ArrayIndex(252)");
+        assert_eq!(JsonPath::from("$.batters[252]").to_string(), "Root -> MemberAccess(batters) -> ArrayIndex(252)");
     }
 
     #[test]
     fn test_json_path_object_root_bracketed_member_access_of_member_access() {
-        assert_eq!(JsonPath::from("$.batters['batter']").to_string(), "ObjectRoot -> MemberAccess(batters) -> MemberAccess(batter)");
+        assert_eq!(JsonPath::from("$.batters['batter']").to_string(), "Root -> MemberAccess(batters) -> MemberAccess(batter)");
     }
 
     #[test]
     fn test_json_path_object_root_array_index_of_bracketed_member_access_of_member_access() {
         assert_eq!(
             JsonPath::from("$.batters['batter'][252]").to_string(),
-            "ObjectRoot -> MemberAccess(batters) -> MemberAccess(batter) -> ArrayIndex(252)"
+            "Root -> MemberAccess(batters) -> MemberAccess(batter) -> ArrayIndex(252)"
         );
     }
 
@@ -677,7 +1074,7 @@ mod tests {
     fn test_json_path_object_root_array_index_of_bracketed_member_access_of_bracketed_member_access() {
         assert_eq!(
             JsonPath::from("$['batters']['batter'][252]").to_string(),
-            "ObjectRoot -> MemberAccess(batters) -> MemberAccess(batter) -> ArrayIndex(252)"
+            "Root -> MemberAccess(batters) -> MemberAccess(batter) -> ArrayIndex(252)"
         );
     }
 
@@ -685,15 +1082,123 @@ mod tests {
     fn test_json_path_object_root_array_index_of_bracketed_member_access_of_bracketed_member_access_mixed_quotes() {
         assert_eq!(
             JsonPath::from("$['batters'][\"batter\"][252]").to_string(),
-            "ObjectRoot -> MemberAccess(batters) -> MemberAccess(batter) -> ArrayIndex(252)"
+            "Root -> MemberAccess(batters) -> MemberAccess(batter) -> ArrayIndex(252)"
+        );
+    }
+
+    #[test]
+    fn test_json_pointer() {
+        assert_eq!(JsonPath::parse_pointer("").unwrap().to_string(), "Root");
+        assert_eq!(
+            JsonPath::parse_pointer("/batters/batter/1/type").unwrap().to_string(),
+            "Root -> MemberAccess(batters) -> MemberAccess(batter) -> Union(MemberAccess(1),ArrayIndex(1)) -> MemberAccess(type)"
+        );
+        assert_eq!(
+            JsonPath::parse_pointer("/a~1b/m~0n/~01").unwrap().to_string(),
+            "Root -> MemberAccess(a/b) -> MemberAccess(m~n) -> MemberAccess(~1)"
         );
+        assert_eq!(JsonPath::parse_pointer("//01/-").unwrap().to_string(), "Root -> MemberAccess() -> MemberAccess(01) -> MemberAccess(-)");
+    }
+
+    #[test]
+    fn test_json_path_errors() {
+        let error = |path: &str| {
+            let err = JsonPath::parse(path).err().unwrap();
+            (err.position, err.expected, err.found)
+        };
+
+        assert_eq!(error("a.b"), (0, String::from("'$'"), String::from("'a'")));
+        assert_eq!(error("$$"), (1, String::from("'.', '[' or the end of the path"), String::from("'$'")));
+        assert_eq!(
+            error("$.a.["),
+            (5, String::from("an index, a slice, a quoted name, '*' or a filter"), String::from("the end of the path"))
+        );
+        assert_eq!(error("$['a"), (4, String::from("a closing \"'\""), String::from("the end of the path")));
+        assert_eq!(error("$[1 2]"), (2, String::from("an array index"), String::from("'1 2'")));
+        assert_eq!(error("$[1:2:3:4]"), (2, String::from("an array slice such as '1:3' or '::2'"), String::from("'1:2:3:4'")));
+        assert_eq!(error("$.a[?(@.b ==)]"), (12, String::from("a path or a value"), String::from("')'")));
+        assert_eq!(error("$.a[?@.b"), (8, String::from("a ']' to close the filter"), String::from("the end of the path")));
+        assert_eq!(
+            error("$.a[?@.b[*] == 1]"),
+            (5, String::from("a path to a single value, with only member names and indices, to compare"), String::from("'@'"))
+        );
+        assert!(JsonPath::parse("$['a$b'][?(@.c == '$')]").is_ok());
+    }
+
+    #[test]
+    fn test_json_path_escaped_member_names() {
+        assert_eq!(JsonPath::from("$['a\\\\b']").to_string(), "Root -> MemberAccess(a\\b)");
+        assert_eq!(JsonPath::from("$[\"a\\\\\\\\\"]").to_string(), "Root -> MemberAccess(a\\\\)");
+        assert_eq!(JsonPath::from("$['a\\tb\\/c']").to_string(), "Root -> MemberAccess(a\tb/c)");
+        assert_eq!(JsonPath::from("$['caf\\u00E9']").to_string(), "Root -> MemberAccess(café)");
+        assert_eq!(JsonPath::from("$['\\uD83D\\uDE00']").to_string(), "Root -> MemberAccess(\u{1f600})");
+        assert_eq!(JsonPath::from("$['\\\"']").non_standard(), Some("'\\\"' inside single quotes"));
+        assert_eq!(JsonPath::from("$['\\'']").non_standard(), None);
+    }
+
+    #[test]
+    fn test_json_path_non_standard_integers() {
+        assert_eq!(JsonPath::from("$[01]").non_standard(), Some("the integer '01' with a leading zero"));
+        assert_eq!(JsonPath::from("$[-0]").non_standard(), Some("the integer '-0' with a leading zero"));
+        assert_eq!(JsonPath::from("$[1, 00]").non_standard(), Some("the integer '00' with a leading zero"));
+        assert_eq!(JsonPath::from("$[:-01]").non_standard(), Some("the integer '-01' with a leading zero"));
+        assert_eq!(JsonPath::from("$[0, 10, -10]").non_standard(), None);
+        assert_eq!(JsonPath::from("$[0:-1:2]").non_standard(), None);
+    }
+
+    #[test]
+    fn test_json_path_escape_errors() {
+        let error = |path: &str| {
+            let err = JsonPath::parse(path).err().unwrap();
+            (err.position, err.expected, err.found)
+        };
+
+        assert_eq!(error("$['a\\qb']"), (5, String::from("an escape character such as 'n' or 'u'"), String::from("'q'")));
+        assert_eq!(error("$['\\u00g0']"), (7, String::from("a hexadecimal digit"), String::from("'g'")));
+        assert_eq!(error("$['\\uDE00']"), (8, String::from("a high surrogate before the low surrogate"), String::from("'0'")));
+        assert_eq!(
+            error("$['\\uD83Dx']"),
+            (9, String::from("a '\\u' escape for the low surrogate after the high surrogate"), String::from("'x'"))
+        );
+        assert_eq!(error("$['\\uD83D\\u0041']"), (14, String::from("a low surrogate after the high surrogate"), String::from("'1'")));
+        assert_eq!(error("$['a\\"), (5, String::from("an escape character such as 'n' or 'u'"), String::from("the end of the path")));
+    }
+
+    #[test]
+    fn test_json_path_empty_deep_scan_errors() {
+        for (path, position) in [("$..", 3), ("$...a", 3), ("$.a..", 5), ("$..['a']..", 10)] {
+            let err = JsonPath::parse(path).err().unwrap();
+            assert_eq!((err.position, err.expected.as_str()), (position, "a member name, '*' or '['"), "{}", path);
+        }
+    }
+
+    #[test]
+    fn test_json_path_error_display() {
+        assert_eq!(
+            JsonPath::parse("$.a(").err().unwrap().to_string(),
+            "expected '.', '[' or the end of the path but found '(' at position 3 of the path\n$.a(\n   ^"
+        );
+    }
+
+    #[test]
+    fn test_json_pointer_errors() {
+        let err = JsonPath::parse_pointer("/a~0b/c~2d").err().unwrap();
+        assert_eq!((err.position, err.expected.as_str(), err.found.as_str()), (8, "'0' or '1' after '~'", "'2'"));
+
+        let err = JsonPath::parse_pointer("a/b").err().unwrap();
+        assert_eq!((err.position, err.expected.as_str(), err.found.as_str()), (0, "'/'", "'a'"));
+    }
+
+    #[test]
+    fn test_json_path_object_root_bracketed_member_access_with_dots() {
+        assert_eq!(JsonPath::from("$.o['j.j'][\"k.k\"]").to_string(), "Root -> MemberAccess(o) -> MemberAccess(j.j) -> MemberAccess(k.k)");
     }
 
     #[test]
     fn test_json_path_object_root_array_index_of_member_access_of_bracketed_member_access() {
         assert_eq!(
             JsonPath::from("$['batters'].batter[252]").to_string(),
-            "ObjectRoot -> MemberAccess(batters) -> MemberAccess(batter) -> ArrayIndex(252)"
+            "Root -> MemberAccess(batters) -> MemberAccess(batter) -> ArrayIndex(252)"
         );
     }
 
@@ -701,7 +1206,7 @@ mod tests {
     fn test_json_path_object_root_array_index_of_bracketed_member_access_of_member_access_escaped_single_quotes() {
         assert_eq!(
             JsonPath::from("$['\\'batters\\''].batter[252]").to_string(),
-            "ObjectRoot -> MemberAccess('batters') -> MemberAccess(batter) -> ArrayIndex(252)"
+            "Root -> MemberAccess('batters') -> MemberAccess(batter) -> ArrayIndex(252)"
         );
     }
 
@@ -709,7 +1214,7 @@ mod tests {
     fn test_json_path_object_root_array_index_of_bracketed_member_access_of_bracketed_member_access_unescaped_single_quotes() {
         assert_eq!(
             JsonPath::from("$[\"'batters'\"].batter[252]").to_string(),
-            "ObjectRoot -> MemberAccess('batters') -> MemberAccess(batter) -> ArrayIndex(252)"
+            "Root -> MemberAccess('batters') -> MemberAccess(batter) -> ArrayIndex(252)"
         );
     }
 
@@ -717,7 +1222,7 @@ mod tests {
     fn test_json_path_object_root_array_index_of_bracketed_member_access_of_member_access_escaped_double_quotes() {
         assert_eq!(
             JsonPath::from("$[\"\\\"batters\\\"\"].batter[252]").to_string(),
-            "ObjectRoot -> MemberAccess(\"batters\") -> MemberAccess(batter) -> ArrayIndex(252)"
+            "Root -> MemberAccess(\"batters\") -> MemberAccess(batter) -> ArrayIndex(252)"
         );
     }
 
@@ -725,7 +1230,7 @@ mod tests {
     fn test_json_path_object_root_array_index_of_bracketed_member_access_of_bracketed_member_access_unescaped_double_quotes() {
         assert_eq!(
             JsonPath::from("$['\"batters\"'].batter[252]").to_string(),
-            "ObjectRoot -> MemberAccess(\"batters\") -> MemberAccess(batter) -> ArrayIndex(252)"
+            "Root -> MemberAccess(\"batters\") -> MemberAccess(batter) -> ArrayIndex(252)"
         );
     }
 
@@ -733,15 +1238,24 @@ mod tests {
     fn test_json_path_object_root_array_slice_of_member_access_of_bracketed_member_access() {
         assert_eq!(
             JsonPath::from("$['batters'].batter[1:10]").to_string(),
-            "ObjectRoot -> MemberAccess(batters) -> MemberAccess(batter) -> ArraySlice(1,10)"
+            "Root -> MemberAccess(batters) -> MemberAccess(batter) -> ArraySlice(1,10)"
         );
     }
 
+    #[test]
+    fn test_json_path_object_root_array_slices_with_steps_and_omitted_bounds() {
+        assert_eq!(JsonPath::from("$.batter[1:10:2]").to_string(), "Root -> MemberAccess(batter) -> ArraySlice(1,10,2)");
+        assert_eq!(JsonPath::from("$.batter[:4]").to_string(), "Root -> MemberAccess(batter) -> ArraySlice(,4)");
+        assert_eq!(JsonPath::from("$.batter[-2:]").to_string(), "Root -> MemberAccess(batter) -> ArraySlice(-2,)");
+        assert_eq!(JsonPath::from("$.batter[::-1]").to_string(), "Root -> MemberAccess(batter) -> ArraySlice(,,-1)");
+        assert_eq!(JsonPath::from("$.batter[-3:-1:]").to_string(), "Root -> MemberAccess(batter) -> ArraySlice(-3,-1)");
+    }
+
     #[test]
     fn test_json_path_object_root_basic_filter_expression() {
         assert_eq!(
             JsonPath::from("$.batters[?(@.color == 'green')]").to_string(),
-            "ObjectRoot -> MemberAccess(batters) -> FilterExpression(@.color == 'green')"
+            "Root -> MemberAccess(batters) -> FilterExpression(@.color == 'green')"
         );
     }
 
@@ -749,7 +1263,37 @@ mod tests {
     fn test_json_path_object_root_complex_filter_expression() {
         assert_eq!(
             JsonPath::from("$.batters[?(@.color == 'green' || (@.color[0] == 'blue' && @.color[1] == 'yellow'))]").to_string(),
-            "ObjectRoot -> MemberAccess(batters) -> FilterExpression(@.color == 'green' || (@.color[0] == 'blue' && @.color[1] == 'yellow'))"
+            "Root -> MemberAccess(batters) -> FilterExpression(@.color == 'green' || (@.color[0] == 'blue' && @.color[1] == 'yellow'))"
+        );
+    }
+
+    #[test]
+    fn test_json_path_object_root_wildcards() {
+        assert_eq!(
+            JsonPath::from("$.toppings.*[*].type").to_string(),
+            "Root -> MemberAccess(toppings) -> Wildcard -> Wildcard -> MemberAccess(type)"
+        );
+        assert_eq!(JsonPath::from("$..*..[*]['*']").to_string(), "Root -> DeepScanWildcard -> DeepScanWildcard -> MemberAccess(*)");
+    }
+
+    #[test]
+    fn test_json_path_object_root_unions() {
+        assert_eq!(
+            JsonPath::from("$.batters.batter[0,2]").to_string(),
+            "Root -> MemberAccess(batters) -> MemberAccess(batter) -> Union(ArrayIndex(0),ArrayIndex(2))"
+        );
+        assert_eq!(JsonPath::from("$['id','name']").to_string(), "Root -> Union(MemberAccess(id),MemberAccess(name))");
+        assert_eq!(
+            JsonPath::from("$.a[ 1 , -2: , * , \"b,c\" , ?@.d == ',' ]").to_string(),
+            "Root -> MemberAccess(a) -> Union(ArrayIndex(1),ArraySlice(-2,),Wildcard,MemberAccess(b,c),FilterExpression(@.d == ','))"
+        );
+    }
+
+    #[test]
+    fn test_json_path_object_root_filter_expression_with_brackets_and_quotes() {
+        assert_eq!(
+            JsonPath::from("$.a[?(@['b]'] == ')' && @.c[?(@ == \"\\\"]\")])].d").to_string(),
+            "Root -> MemberAccess(a) -> FilterExpression(@['b]'] == ')' && @.c[?(@ == \"\\\"]\")]) -> MemberAccess(d)"
         );
     }
 
@@ -757,7 +1301,7 @@ mod tests {
     fn test_json_path_object_root_filter_basic_expression_array_slice_of_array_index_of_member_access_of_bracketed_member_access_with_unescaped_single_quotes() {
         assert_eq!(
             JsonPath::from("$[\"'batters'\"].batter[252][1:10][?(@.color == 'blue')]").to_string(),
-            "ObjectRoot -> MemberAccess('batters') -> MemberAccess(batter) -> ArrayIndex(252) -> ArraySlice(1,10) -> FilterExpression(@.color == 'blue')"
+            "Root -> MemberAccess('batters') -> MemberAccess(batter) -> ArrayIndex(252) -> ArraySlice(1,10) -> FilterExpression(@.color == 'blue')"
         )
     }
 
@@ -765,7 +1309,7 @@ mod tests {
     fn test_json_path_object_root_complex_filter_expression_array_slice_of_array_index_of_member_access_of_bracketed_member_access_with_unescaped_single_quotes() {
         assert_eq!(
             JsonPath::from("$[\"'batters'\"].batter[252][1:10][?(@.color == 'green' || (@.color[0] == 'blue' && @.color[1] == 'yellow'))]").to_string(),
-            "ObjectRoot -> MemberAccess('batters') -> MemberAccess(batter) -> ArrayIndex(252) -> ArraySlice(1,10) -> FilterExpression(@.color == 'green' || (@.color[0] == 'blue' && @.color[1] == 'yellow'))"
+            "Root -> MemberAccess('batters') -> MemberAccess(batter) -> ArrayIndex(252) -> ArraySlice(1,10) -> FilterExpression(@.color == 'green' || (@.color[0] == 'blue' && @.color[1] == 'yellow'))"
         )
     }
 }
@@ -1,231 +1,1480 @@
+use std::collections::VecDeque;
+
 use super::{
+    filter::JsonFilterRun,
     lexer::JsonToken,
     path::{JsonPath, JsonPathOperator},
 };
 
+/// Whether a query captures the values at the end of the path or the names of the members holding them. A matched value
+/// with no name, i.e. an array item or the root, has its first token captured in its place in `Key` mode.
+#[derive(Clone, Copy, PartialEq)]
+pub enum JsonQueryMode {
+    Value,
+    Key,
+}
+
+pub enum JsonQueryStatus {
+    None,
+    /// `path` is the normalized path of the match a token starts, e.g. `$['toppings']['topping'][3]`.
+    Token {
+        token: JsonToken,
+        capture: bool,
+        boundary: JsonMatchBoundary,
+        path: Option<String>,
+    },
+}
+
+/// Where a token sits in a match. A scalar is a whole match on its own, an object or array starts with its opening token
+/// and ends with its closing one. The whitespace around a match is captured but isn't part of it.
+///
+/// Matches can be nested, e.g. `$..*` matches an object and each of its members, so a token can be in several matches at
+/// once but it only ever starts or ends the innermost of them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum JsonMatchBoundary {
+    None,
+    Start,
+    End,
+    Whole,
+}
+
+/// Runs a JSON path over a stream of tokens, handing each token back along with whether it is part of a match and whether
+/// it starts or ends one.
+///
+/// Whether an array item is selected can depend on how long the array is, e.g. `[-2:]`. The tokens of such an item are held
+/// back until enough of the array has gone past to decide, and everything after them waits too so tokens always come back
+/// in the order they were pushed.
 pub struct JsonQuery<'a> {
     path: JsonPathCursor<'a>,
-    current_match_ended: bool,
-    current_match_depth: isize,
+    mode: JsonQueryMode,
+    tokens: VecDeque<JsonQueuedToken>,
+}
+
+/// A token waiting to be handed back. Whether it starts or ends a match depends on the capture of the match itself, not of
+/// everything around it, so that is kept separately.
+struct JsonQueuedToken {
+    token: JsonToken,
+    capture: JsonCapture,
+    boundary: JsonMatchBoundary,
+    is_match: JsonCapture,
+    path: Option<String>,
 }
 
 impl<'a> JsonQuery<'a> {
-    pub fn from(path: &'a JsonPath) -> JsonQuery {
+    pub fn from(path: &'a JsonPath) -> JsonQuery<'a> {
+        JsonQuery::with_mode(path, JsonQueryMode::Value)
+    }
+
+    pub fn with_mode(path: &'a JsonPath, mode: JsonQueryMode) -> JsonQuery<'a> {
         JsonQuery {
             path: JsonPathCursor::from(path),
-            current_match_ended: false,
-            current_match_depth: -1,
+            mode,
+            tokens: VecDeque::new(),
+        }
+    }
+
+    pub fn push_token(&mut self, token: JsonToken) {
+        self.path.release_selections(&self.tokens);
+
+        let queued = self.path.parse(token, self.mode);
+
+        self.tokens.push_back(queued);
+    }
+
+    pub fn pop_token(&mut self) -> JsonQueryStatus {
+        let (capture, is_match) = match self.tokens.front().map(|queued| (self.path.decide(&queued.capture), self.path.decide(&queued.is_match))) {
+            Some((Some(capture), Some(is_match))) => (capture, is_match),
+            _ => return JsonQueryStatus::None,
+        };
+
+        match self.tokens.pop_front() {
+            Some(queued) if is_match => JsonQueryStatus::Token {
+                token: queued.token,
+                capture,
+                boundary: queued.boundary,
+                path: queued.path,
+            },
+            Some(queued) => JsonQueryStatus::Token {
+                token: queued.token,
+                capture,
+                boundary: JsonMatchBoundary::None,
+                path: None,
+            },
+            None => JsonQueryStatus::None,
         }
     }
 
-    pub fn parse(&mut self, token: &JsonToken) -> bool {
-        let before_parse_match_state = self.path.is_matching();
+    /// Lets go of any tokens still held back at the end of the input. Selections that are still undecided, which can only
+    /// happen if the input ended partway through an array, are treated as not selected.
+    pub fn close(&mut self) {
+        self.path.close();
+    }
+}
+
+/// Whether a token is part of a match. A token that is only part of a match if array items are selected that haven't
+/// been decided yet is captured if every selection in any one of the lists turns out to be met.
+#[derive(Clone)]
+enum JsonCapture {
+    Never,
+    Always,
+    Depends(Vec<Vec<usize>>),
+}
+
+/// How far along the path a value in the document has got, along with the undecided selections it got there through.
+#[derive(Clone, PartialEq)]
+struct JsonPathState {
+    position: usize,
+    selections: Vec<usize>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum JsonSlotPhase {
+    Empty,
+    Name,
+    Leading,
+    Trailing,
+    Closed,
+}
+
+/// The entry of an object or array that the document is currently in. The whitespace before an entry's value and after
+/// a scalar value is counted as part of the entry, the name, `:` and delimiters are not.
+struct JsonSlot<'a> {
+    phase: JsonSlotPhase,
+    states: Vec<JsonPathState>,
+    filters: Vec<JsonFilterCandidate<'a>>,
+    name_capture: JsonCapture,
+    capture: JsonCapture,
+}
+
+/// An item whose selection depends on the length of its array, which is decided as more of the array goes past.
+struct JsonCandidate<'a> {
+    index: isize,
+    operator: &'a JsonPathOperator,
+    selection: usize,
+}
+
+/// An entry that is selected if its value passes a filter. The filter is run over the value's tokens as they go past and
+/// is let go of as soon as it has decided, which for `@.type == 'Chocolate'` can be well before the value ends.
+struct JsonFilterCandidate<'a> {
+    selection: usize,
+    depth: usize,
+    run: JsonFilterRun<'a>,
+}
+
+/// An object or array that is open in the document. The bottom frame stands in for the document itself, with the root
+/// value as its only entry.
+struct JsonPathFrame<'a> {
+    is_array: bool,
+    states: Vec<JsonPathState>,
+    capture: JsonCapture,
+    index: isize,
+    name: String,
+    candidates: Vec<JsonCandidate<'a>>,
+    slot: JsonSlot<'a>,
+}
+
+/// Whether each selection is met, or `None` while it's undecided. A selection's slot is reused once nothing refers to it
+/// any more, so this stays as long as the selections in use rather than growing with the document.
+struct JsonSelections {
+    values: Vec<Option<bool>>,
+    free: Vec<usize>,
+    release_at: usize,
+}
+
+impl JsonSelections {
+    fn new() -> JsonSelections {
+        JsonSelections {
+            values: Vec::new(),
+            free: Vec::new(),
+            release_at: 16,
+        }
+    }
 
-        match token {
-            JsonToken::PropertyName { raw: _, name } => self.path.member_access(name),
-            JsonToken::ObjectOpen(_) => self.path.traverse(),
-            JsonToken::ObjectClose(_) => self.path.recede(),
-            JsonToken::ArrayOpen(_) => {
-                self.path.traverse();
-                self.path.increment_index();
+    /// Adds an undecided selection, giving its index.
+    fn push(&mut self) -> usize {
+        match self.free.pop() {
+            Some(selection) => {
+                self.values[selection] = None;
+                selection
             }
-            JsonToken::ArrayClose(_) => self.path.recede(),
-            JsonToken::ArrayItemDelimiter(_) => {
-                self.path.increment_index();
+            None => {
+                self.values.push(None);
+                self.values.len() - 1
             }
-            _ => {}
+        }
+    }
+
+    /// Frees every selection that isn't marked as in use. This only happens once there are no free slots left and twice
+    /// as many selections as there were in use last time, so it is only ever run every so often.
+    fn release(&mut self, mark: impl FnOnce(&mut Vec<bool>)) {
+        if !self.free.is_empty() || self.values.len() < self.release_at {
+            return;
         }
 
-        let mut is_matching = self.path.is_matching();
+        let mut in_use = vec![false; self.values.len()];
+        mark(&mut in_use);
 
-        let matching_just_started = !before_parse_match_state && is_matching;
+        self.free = (0..in_use.len()).filter(|selection| !in_use[*selection]).collect();
+        self.release_at = self.release_at.max(2 * (self.values.len() - self.free.len()));
+    }
+}
+
+impl std::ops::Index<usize> for JsonSelections {
+    type Output = Option<bool>;
+
+    fn index(&self, selection: usize) -> &Option<bool> {
+        &self.values[selection]
+    }
+}
 
-        if !is_matching && self.current_match_ended {
-            self.current_match_ended = false;
+impl std::ops::IndexMut<usize> for JsonSelections {
+    fn index_mut(&mut self, selection: usize) -> &mut Option<bool> {
+        &mut self.values[selection]
+    }
+}
+
+struct JsonPathCursor<'a> {
+    path: &'a JsonPath,
+    frames: Vec<JsonPathFrame<'a>>,
+    selections: JsonSelections,
+    filters: Vec<JsonFilterCandidate<'a>>,
+}
+
+impl<'a> JsonPathCursor<'a> {
+    fn from(path: &'a JsonPath) -> JsonPathCursor<'a> {
+        // The root is the only entry of the document, whether it's an object, an array or a scalar.
+        let states = match path.operations().first() {
+            Some(_) => vec![JsonPathState { position: 1, selections: Vec::new() }],
+            None => Vec::new(),
+        };
+
+        let mut cursor = JsonPathCursor {
+            path,
+            frames: Vec::new(),
+            selections: JsonSelections::new(),
+            filters: Vec::new(),
+        };
+
+        let slot = cursor.slot(&JsonCapture::Never, states, Vec::new(), JsonSlotPhase::Leading);
+
+        cursor.frames.push(JsonPathFrame {
+            is_array: false,
+            states: Vec::new(),
+            capture: JsonCapture::Never,
+            index: 0,
+            name: String::new(),
+            candidates: Vec::new(),
+            slot,
+        });
+
+        cursor
+    }
+
+    /// Moves the cursor on past a token, working out whether it is captured and whether it starts or ends a match, along
+    /// with the path of the match it starts.
+    fn parse(&mut self, token: JsonToken, mode: JsonQueryMode) -> JsonQueuedToken {
+        // A value is a match if the entry it's the value of is, which is the entry that's open before the value starts.
+        let value_match = self.frame().slot.name_capture.clone();
+        let value_path = match value_match {
+            JsonCapture::Never => None,
+            _ => Some(self.normalized_path()),
+        };
+        let is_unnamed = self.frame().is_array || self.frames.len() == 1;
+
+        let capture = match &token {
+            JsonToken::ObjectOpen(_) | JsonToken::ArrayOpen(_) => {
+                let capture = self.start_value(JsonSlotPhase::Closed);
+                let is_array = matches!(token, JsonToken::ArrayOpen(_));
+                let len = self.path.operations().len();
+                let states = self.frame().slot.states.iter().filter(|state| state.position < len).cloned().collect();
+
+                self.frames.push(JsonPathFrame {
+                    is_array,
+                    states,
+                    capture: capture.clone(),
+                    index: -1,
+                    name: String::new(),
+                    candidates: Vec::new(),
+                    slot: self.slot(&capture, Vec::new(), Vec::new(), JsonSlotPhase::Empty),
+                });
+
+                if is_array {
+                    self.start_item();
+                }
+
+                capture
+            }
+            JsonToken::ObjectClose(_) | JsonToken::ArrayClose(_) => {
+                self.end_container();
+                self.frame().slot.capture.clone()
+            }
+            JsonToken::PropertyName { raw: _, name } => {
+                self.frame_mut().name.clone_from(name);
+
+                let (states, filters) = self.select(|operator| match operator {
+                    JsonPathOperator::MemberAccess(path_member) => Some(*name == *path_member),
+                    JsonPathOperator::DeepScanMemberAccess(path_member) => Some(*name == *path_member),
+                    JsonPathOperator::Wildcard | JsonPathOperator::DeepScanWildcard => Some(true),
+                    _ => Some(false),
+                });
+                let capture = self.frame().capture.clone();
+                let slot = self.slot(&capture, states, filters, JsonSlotPhase::Name);
+
+                self.set_slot(slot);
+
+                capture
+            }
+            JsonToken::KeyValueDelimiter(_) => {
+                self.frame_mut().slot.phase = JsonSlotPhase::Leading;
+                self.frame().capture.clone()
+            }
+            JsonToken::PropertyDelimiter(_) => {
+                self.frame_mut().slot.phase = JsonSlotPhase::Empty;
+                self.frame().capture.clone()
+            }
+            JsonToken::ArrayItemDelimiter(_) => {
+                let capture = self.frame().capture.clone();
+                self.start_item();
+                capture
+            }
+            JsonToken::Whitespace(_) | JsonToken::NewLine(_) => match self.frame().slot.phase {
+                JsonSlotPhase::Leading | JsonSlotPhase::Trailing => self.frame().slot.capture.clone(),
+                _ => self.frame().capture.clone(),
+            },
+            _ => self.start_value(JsonSlotPhase::Trailing),
+        };
+
+        self.run_filters(&token);
+
+        let (capture, boundary, is_match, path) = match (mode, &token) {
+            (JsonQueryMode::Value, JsonToken::ObjectOpen(_) | JsonToken::ArrayOpen(_)) => (capture, JsonMatchBoundary::Start, value_match, value_path),
+            // The container has been closed by now so the entry it was the value of is open again.
+            (JsonQueryMode::Value, JsonToken::ObjectClose(_) | JsonToken::ArrayClose(_)) => (capture, JsonMatchBoundary::End, self.frame().slot.name_capture.clone(), None),
+            (
+                JsonQueryMode::Value,
+                JsonToken::PropertyName { raw: _, name: _ }
+                | JsonToken::KeyValueDelimiter(_)
+                | JsonToken::PropertyDelimiter(_)
+                | JsonToken::ArrayItemDelimiter(_)
+                | JsonToken::Whitespace(_)
+                | JsonToken::NewLine(_),
+            ) => (capture, JsonMatchBoundary::None, JsonCapture::Never, None),
+            (JsonQueryMode::Value, _) => (capture, JsonMatchBoundary::Whole, value_match, value_path),
+            // Only the name that made the path match is captured, not the names of members nested in its value.
+            (JsonQueryMode::Key, JsonToken::PropertyName { raw: _, name: _ }) => {
+                let name_capture = self.frame().slot.name_capture.clone();
+                (name_capture.clone(), JsonMatchBoundary::Whole, name_capture, Some(self.normalized_path()))
+            }
+            (JsonQueryMode::Key, JsonToken::ObjectOpen(_) | JsonToken::ArrayOpen(_)) if is_unnamed => (value_match.clone(), JsonMatchBoundary::Start, value_match, value_path),
+            (
+                JsonQueryMode::Key,
+                JsonToken::ObjectClose(_)
+                | JsonToken::ArrayClose(_)
+                | JsonToken::KeyValueDelimiter(_)
+                | JsonToken::PropertyDelimiter(_)
+                | JsonToken::ArrayItemDelimiter(_)
+                | JsonToken::Whitespace(_)
+                | JsonToken::NewLine(_),
+            ) => (JsonCapture::Never, JsonMatchBoundary::None, JsonCapture::Never, None),
+            (JsonQueryMode::Key, _) if is_unnamed => (value_match.clone(), JsonMatchBoundary::Whole, value_match, value_path),
+            (JsonQueryMode::Key, _) => (JsonCapture::Never, JsonMatchBoundary::None, JsonCapture::Never, None),
+        };
+
+        JsonQueuedToken {
+            token,
+            capture,
+            boundary,
+            is_match,
+            path,
         }
+    }
 
-        if is_matching {
-            match token {
-                JsonToken::PropertyName { raw: _, name: _ } => {
-                    if self.current_match_depth <= 0 {
-                        self.current_match_depth = 0;
-                        is_matching = false;
-                    }
+    /// Gives the normalized path of the entry that is open, the way RFC 9535 writes it, e.g. `$['toppings']['topping'][3]`.
+    fn normalized_path(&self) -> String {
+        let mut path = String::from("$");
+
+        for frame in &self.frames[1..] {
+            match frame.is_array {
+                true => path.push_str(format!("[{}]", frame.index).as_str()),
+                false => {
+                    path.push_str("['");
+                    push_normalized_name(&mut path, &frame.name);
+                    path.push_str("']");
                 }
-                JsonToken::ObjectOpen(_) => self.current_match_depth += 1,
-                JsonToken::ObjectClose(_) => {
-                    self.current_match_depth -= 1;
+            }
+        }
+
+        path
+    }
+
+    /// Works out whether a token is captured, or `None` if that still depends on selections that haven't been decided.
+    fn decide(&self, capture: &JsonCapture) -> Option<bool> {
+        match capture {
+            JsonCapture::Never => Some(false),
+            JsonCapture::Always => Some(true),
+            JsonCapture::Depends(alternatives) => {
+                let mut is_undecided = false;
 
-                    if self.current_match_depth == 0 {
-                        self.current_match_ended = true;
-                        return is_matching;
+                for selections in alternatives {
+                    match self.decide_all(selections) {
+                        Some(true) => return Some(true),
+                        Some(false) => {}
+                        None => is_undecided = true,
                     }
                 }
-                JsonToken::ArrayOpen(_) => self.current_match_depth += 1,
-                JsonToken::ArrayClose(_) => {
-                    self.current_match_depth -= 1;
 
-                    if self.current_match_depth == 0 {
-                        self.current_match_ended = true;
-                        return is_matching;
-                    }
+                match is_undecided {
+                    true => None,
+                    false => Some(false),
                 }
-                JsonToken::ArrayItemDelimiter(_) => {
-                    if self.current_match_depth <= 0 {
-                        if !matching_just_started {
-                            self.current_match_ended = true;
-                        }
-                        self.current_match_depth = 0;
-                        is_matching = false;
+            }
+        }
+    }
+
+    fn decide_all(&self, selections: &[usize]) -> Option<bool> {
+        let mut is_undecided = false;
+
+        for selection in selections {
+            match self.selections[*selection] {
+                Some(true) => {}
+                Some(false) => return Some(false),
+                None => is_undecided = true,
+            }
+        }
+
+        match is_undecided {
+            true => None,
+            false => Some(true),
+        }
+    }
+
+    fn close(&mut self) {
+        for mut filter in self.filters.drain(..) {
+            filter.run.close();
+            self.selections[filter.selection] = filter.run.decide();
+        }
+
+        for selection in self.selections.values.iter_mut() {
+            if selection.is_none() {
+                *selection = Some(false);
+            }
+        }
+    }
+
+    /// Frees the selections that nothing refers to any more, which are those that aren't part of the captures of the open
+    /// objects and arrays, their entries, the filters and candidates still running or the tokens still waiting in `queued`.
+    fn release_selections(&mut self, queued: &VecDeque<JsonQueuedToken>) {
+        let frames = &self.frames;
+        let filters = &self.filters;
+
+        self.selections.release(|in_use| {
+            let mut mark_capture = |capture: &JsonCapture| {
+                if let JsonCapture::Depends(alternatives) = capture {
+                    for selection in alternatives.iter().flatten() {
+                        in_use[*selection] = true;
                     }
                 }
-                JsonToken::PropertyDelimiter(_) => {
-                    if self.current_match_depth <= 0 {
-                        self.current_match_ended = true;
-                        self.current_match_depth = 0;
-                        is_matching = false;
-                    }
+            };
+
+            for queued in queued {
+                mark_capture(&queued.capture);
+                mark_capture(&queued.is_match);
+            }
+
+            for frame in frames {
+                mark_capture(&frame.capture);
+                mark_capture(&frame.slot.capture);
+                mark_capture(&frame.slot.name_capture);
+            }
+
+            let states = frames.iter().flat_map(|frame| frame.states.iter().chain(frame.slot.states.iter()));
+            let candidates = frames.iter().flat_map(|frame| frame.candidates.iter().map(|candidate| candidate.selection));
+            let filters = frames.iter().flat_map(|frame| frame.slot.filters.iter()).chain(filters.iter()).map(|filter| filter.selection);
+
+            for selection in states.flat_map(|state| state.selections.iter().copied()).chain(candidates).chain(filters) {
+                in_use[selection] = true;
+            }
+        });
+    }
+
+    fn frame(&self) -> &JsonPathFrame<'a> {
+        &self.frames[self.frames.len() - 1]
+    }
+
+    fn frame_mut(&mut self) -> &mut JsonPathFrame<'a> {
+        let last = self.frames.len() - 1;
+        &mut self.frames[last]
+    }
+
+    /// Starts the entry at the next index of the array that is open.
+    fn start_item(&mut self) {
+        self.frame_mut().index += 1;
+
+        let index = self.frame().index;
+        let (states, filters) = self.select(|operator| is_selected(operator, index, index + 1, false));
+        let capture = self.frame().capture.clone();
+        let slot = self.slot(&capture, states, filters, JsonSlotPhase::Leading);
+
+        self.set_slot(slot);
+    }
+
+    /// Moves on to the next entry of the open object or array. Filters on the entry before it that never got a value to
+    /// run over, such as the item that would have followed a trailing `,`, are not met.
+    fn set_slot(&mut self, slot: JsonSlot<'a>) {
+        let last = self.frames.len() - 1;
+        let slot = std::mem::replace(&mut self.frames[last].slot, slot);
+
+        for filter in slot.filters {
+            self.selections[filter.selection] = Some(false);
+        }
+    }
+
+    /// Handles the first token of a value, which is either a scalar or the opening of an object or array.
+    fn start_value(&mut self, phase: JsonSlotPhase) -> JsonCapture {
+        if self.frame().is_array {
+            // The item now definitely exists so the array is at least this long.
+            let len = self.frame().index + 1;
+            self.decide_candidates(len, false);
+        }
+
+        let filters = std::mem::take(&mut self.frame_mut().slot.filters);
+
+        self.filters.extend(filters);
+        self.frame_mut().slot.phase = phase;
+        self.frame().slot.capture.clone()
+    }
+
+    /// Passes a token on to the filters whose values it is part of, deciding their selections where it can.
+    fn run_filters(&mut self, token: &JsonToken) {
+        let selections = &mut self.selections;
+
+        self.filters.retain_mut(|filter| {
+            match token {
+                JsonToken::ObjectOpen(_) | JsonToken::ArrayOpen(_) => filter.depth += 1,
+                JsonToken::ObjectClose(_) | JsonToken::ArrayClose(_) => filter.depth -= 1,
+                _ => {}
+            }
+
+            filter.run.push_token(token);
+
+            if filter.depth == 0 {
+                filter.run.close();
+            }
+
+            match filter.run.decide() {
+                Some(is_met) => {
+                    selections[filter.selection] = Some(is_met);
+                    false
                 }
-                JsonToken::KeyValueDelimiter(_) => {
-                    if self.current_match_depth <= 0 {
-                        self.current_match_depth = 0;
-                        is_matching = false;
+                None => true,
+            }
+        });
+    }
+
+    fn end_container(&mut self) {
+        if self.frames.len() < 2 {
+            return;
+        }
+
+        if self.frame().is_array {
+            let len = match self.frame().slot.phase {
+                JsonSlotPhase::Leading => self.frame().index,
+                _ => self.frame().index + 1,
+            };
+
+            self.decide_candidates(len, true);
+        }
+
+        if let Some(frame) = self.frames.pop() {
+            for filter in frame.slot.filters {
+                self.selections[filter.selection] = Some(false);
+            }
+        }
+    }
+
+    /// Decides the candidates of the open array that can be decided now that it is known to have at least `len` items, or
+    /// exactly `len` items if it has closed.
+    fn decide_candidates(&mut self, len: isize, is_closed: bool) {
+        let last = self.frames.len() - 1;
+        let frame = &mut self.frames[last];
+        let selections = &mut self.selections;
+
+        frame.candidates.retain(|candidate| match is_selected(candidate.operator, candidate.index, len, is_closed) {
+            Some(is_selected) => {
+                selections[candidate.selection] = Some(is_selected);
+                false
+            }
+            None => true,
+        });
+    }
+
+    /// Moves every path state of the open object or array past the operator it is at, if the entry that is starting is
+    /// selected by it. An entry whose selection can't be decided yet becomes a candidate of the array.
+    ///
+    /// A deep scan also stays where it is for every entry, so the entries of the entry are scanned in turn. That way a
+    /// member is found at any depth, including inside another match, while only keeping track of the open containers.
+    ///
+    /// An entry is always undecided by a filter, which is handed back to be run once the entry's value starts. An entry is
+    /// selected by a union if it is selected by any of the union's selectors.
+    fn select<F>(&mut self, is_selected: F) -> (Vec<JsonPathState>, Vec<JsonFilterCandidate<'a>>)
+    where
+        F: Fn(&JsonPathOperator) -> Option<bool>,
+    {
+        let path: &'a JsonPath = self.path;
+        let operations = path.operations();
+        let last = self.frames.len() - 1;
+        let frame = &mut self.frames[last];
+        let mut states = Vec::new();
+        let mut filters = Vec::new();
+
+        for state in &frame.states {
+            let operator = &operations[state.position];
+
+            if matches!(operator, JsonPathOperator::DeepScanMemberAccess(_) | JsonPathOperator::DeepScanWildcard | JsonPathOperator::DeepScan(_)) && !states.contains(state) {
+                states.push(state.clone());
+            }
+
+            // An entry picked out by more than one selector of a union is still only matched once.
+            let selectors = match operator {
+                JsonPathOperator::Union(selectors) => selectors.as_slice(),
+                JsonPathOperator::DeepScan(selector) => match selector.as_ref() {
+                    JsonPathOperator::Union(selectors) => selectors.as_slice(),
+                    selector => std::slice::from_ref(selector),
+                },
+                _ => std::slice::from_ref(operator),
+            };
+
+            for selector in selectors {
+                let next = match (selector, is_selected(selector)) {
+                    (JsonPathOperator::FilterExpression(filter), _) => {
+                        let selection = self.selections.push();
+
+                        filters.push(JsonFilterCandidate {
+                            selection,
+                            depth: 0,
+                            run: JsonFilterRun::from(filter),
+                        });
+
+                        let mut selections = state.selections.clone();
+                        selections.push(selection);
+
+                        JsonPathState {
+                            position: state.position + 1,
+                            selections,
+                        }
                     }
+                    (_, Some(true)) => JsonPathState {
+                        position: state.position + 1,
+                        selections: state.selections.clone(),
+                    },
+                    (_, Some(false)) => continue,
+                    (_, None) => {
+                        let selection = self.selections.push();
+
+                        frame.candidates.push(JsonCandidate {
+                            index: frame.index,
+                            operator: selector,
+                            selection,
+                        });
+
+                        let mut selections = state.selections.clone();
+                        selections.push(selection);
+
+                        JsonPathState {
+                            position: state.position + 1,
+                            selections,
+                        }
+                    }
+                };
+
+                if !states.contains(&next) {
+                    states.push(next);
                 }
-                _ => {}
             }
         }
 
-        if self.current_match_ended {
-            false
-        } else {
-            is_matching
+        (states, filters)
+    }
+
+    /// Creates an entry that has got as far as `states` along the path. It is a match if any of them has reached the end.
+    fn slot(&self, capture: &JsonCapture, states: Vec<JsonPathState>, filters: Vec<JsonFilterCandidate<'a>>, phase: JsonSlotPhase) -> JsonSlot<'a> {
+        let len = self.path.operations().len();
+        let matches: Vec<Vec<usize>> = states.iter().filter(|state| state.position == len).map(|state| state.selections.clone()).collect();
+        let name_capture = self.capture(matches);
+
+        JsonSlot {
+            phase,
+            capture: self.combine(capture, &name_capture),
+            name_capture,
+            states,
+            filters,
+        }
+    }
+
+    fn capture(&self, alternatives: Vec<Vec<usize>>) -> JsonCapture {
+        let mut undecided = Vec::new();
+
+        for selections in alternatives {
+            match self.decide_all(&selections) {
+                Some(true) => return JsonCapture::Always,
+                Some(false) => {}
+                None => undecided.push(selections),
+            }
+        }
+
+        match undecided.is_empty() {
+            true => JsonCapture::Never,
+            false => JsonCapture::Depends(undecided),
+        }
+    }
+
+    fn combine(&self, first: &JsonCapture, second: &JsonCapture) -> JsonCapture {
+        match (first, second) {
+            (JsonCapture::Always, _) | (_, JsonCapture::Always) => JsonCapture::Always,
+            (JsonCapture::Never, capture) | (capture, JsonCapture::Never) => capture.clone(),
+            (JsonCapture::Depends(first), JsonCapture::Depends(second)) => self.capture(first.iter().chain(second.iter()).cloned().collect()),
         }
     }
 }
 
-struct JsonPathCursor<'a> {
-    path: &'a JsonPath,
-    path_cursor: usize,
-    document_cursor: usize,
-    document_array_cursors: Vec<isize>,
-    path_aligned: bool,
-    path_match: bool,
+/// Escapes a member name for a normalized path. Only `'`, `\` and control characters are escaped, which use the short
+/// forms where JSON has them and `\u00XX` otherwise.
+fn push_normalized_name(path: &mut String, name: &str) {
+    for c in name.chars() {
+        match c {
+            '\'' => path.push_str("\\'"),
+            '\\' => path.push_str("\\\\"),
+            '\u{8}' => path.push_str("\\b"),
+            '\u{c}' => path.push_str("\\f"),
+            '\n' => path.push_str("\\n"),
+            '\r' => path.push_str("\\r"),
+            '\t' => path.push_str("\\t"),
+            c if c < ' ' => path.push_str(format!("\\u{:04x}", c as u32).as_str()),
+            c => path.push(c),
+        }
+    }
 }
 
-impl<'a> JsonPathCursor<'a> {
-    fn from(path: &'a JsonPath) -> JsonPathCursor {
-        JsonPathCursor {
-            path,
-            path_cursor: 0,
-            document_cursor: 0,
-            document_array_cursors: Vec::new(),
-            path_aligned: true,
-            path_match: false,
+/// Works out whether the array item at `index` is selected by `operator`. Until the array closes all that is known is that
+/// it has at least `len` items, so `None` is returned if the answer depends on how many more there are.
+fn is_selected(operator: &JsonPathOperator, index: isize, len: isize, is_closed: bool) -> Option<bool> {
+    match operator {
+        JsonPathOperator::ArrayIndex(path_index) if *path_index >= 0 => Some(index == *path_index),
+        // A negative index counts back from the end, so an item is ruled out once enough items have followed it.
+        JsonPathOperator::ArrayIndex(path_index) if is_closed || index < len + path_index => Some(index == len + path_index),
+        JsonPathOperator::ArrayIndex(_) => None,
+        JsonPathOperator::ArraySlice(start, end, step) => is_in_slice(*start, *end, step.unwrap_or(1), index, len, is_closed),
+        JsonPathOperator::Wildcard | JsonPathOperator::DeepScanWildcard => Some(true),
+        _ => Some(false),
+    }
+}
+
+/// Works out whether the array item at `index` is in a slice, following RFC 9535. Negative bounds count back from the end
+/// of the array so until it closes they can only rule items out, or in the case of `end` rule them in once enough items
+/// have followed them. A slice with a negative step picks out its items in reverse but they are still matched in the
+/// order they appear in the document, so with a step of `-1` it picks out the same items as the slice the other way
+/// round and is decided just as early. With a larger negative step the items picked out are counted from `start`, which
+/// is the last item unless `start` is given and not negative, so until then it isn't known which of them are picked.
+fn is_in_slice(start: Option<isize>, end: Option<isize>, step: isize, index: isize, len: isize, is_closed: bool) -> Option<bool> {
+    let normalize = |bound: isize| if bound >= 0 { bound } else { len + bound };
+
+    if step == 0 {
+        return Some(false);
+    }
+
+    if is_closed && step > 0 {
+        let lower = start.map_or(0, normalize).clamp(0, len);
+        let upper = end.map_or(len, normalize).clamp(0, len);
+
+        return Some(lower <= index && index < upper && (index - lower) % step == 0);
+    }
+
+    if is_closed {
+        let upper = start.map_or(len - 1, normalize).clamp(-1, len - 1);
+        let lower = end.map_or(-1, normalize).clamp(-1, len - 1);
+
+        return Some(lower < index && index <= upper && (upper - index) % -step == 0);
+    }
+
+    if step < 0 {
+        match end {
+            Some(end) if end >= 0 && index <= end => return Some(false),
+            Some(end) if end < 0 && index <= len + end => return Some(false),
+            Some(end) if end < 0 => return None,
+            _ => {}
         }
+
+        return match start {
+            Some(start) if start >= 0 && index > start => Some(false),
+            Some(start) if start >= 0 && start < len => Some((start - index) % -step == 0),
+            Some(start) if start < 0 && index > len + start => None,
+            _ if step == -1 => Some(true),
+            _ => None,
+        };
+    }
+
+    match start.unwrap_or(0) {
+        start if start >= 0 && (index < start || (index - start) % step != 0) => return Some(false),
+        start if start < 0 && index < len + start => return Some(false),
+        _ => {}
     }
 
-    fn is_array_root(&self) -> bool {
-        match &self.path.operations()[self.path_cursor] {
-            JsonPathOperator::ArrayRoot(_) => true,
-            _ => false,
+    match (start, end) {
+        (_, Some(end)) if end >= 0 && index >= end => Some(false),
+        (Some(start), _) if start < 0 => None,
+        (_, Some(end)) if end < 0 && index < len + end => Some(true),
+        (_, Some(end)) if end < 0 => None,
+        _ => Some(true),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json::filter::{JsonValue, JsonValueBuilder};
+    use crate::json::lexer::{JsonStreamLexer, JsonStreamStatus};
+    use std::fs;
+    use std::path::Path;
+
+    fn captured(json: &str, path: &str, mode: JsonQueryMode) -> String {
+        captured_by(json, &JsonPath::from(path), mode)
+    }
+
+    fn captured_by(json: &str, json_path: &JsonPath, mode: JsonQueryMode) -> String {
+        let mut query = JsonQuery::with_mode(json_path, mode);
+        let mut json_lexer = JsonStreamLexer::new();
+        let mut tokens = Vec::new();
+        let mut output = String::new();
+
+        for c in json.chars() {
+            json_lexer.push_char(c).unwrap();
+
+            while let JsonStreamStatus::Token(token) = json_lexer.pop_token() {
+                tokens.push(token);
+            }
+        }
+
+        json_lexer.close().unwrap();
+
+        while let JsonStreamStatus::Token(token) = json_lexer.pop_token() {
+            tokens.push(token);
+        }
+
+        for token in tokens {
+            query.push_token(token);
+
+            while let JsonQueryStatus::Token { token, capture, .. } = query.pop_token() {
+                if capture {
+                    output.push_str(token.raw());
+                }
+            }
         }
+
+        query.close();
+
+        while let JsonQueryStatus::Token { token, capture, .. } = query.pop_token() {
+            if capture {
+                output.push_str(token.raw());
+            }
+        }
+
+        output
     }
 
-    fn traverse(&mut self) {
-        if !self.is_array_root() && self.path_cursor != self.document_cursor {
-            self.document_cursor += 1;
-            return;
-        } else {
-            self.document_cursor += 1;
+    #[test]
+    fn test_key_mode() {
+        let json = "{ \"id\": 1, \"batters\": { \"id\": 2, \"batter\": [ { \"id\": 3 } ] } }";
+
+        assert_eq!(captured(json, "$.batters", JsonQueryMode::Value), " { \"id\": 2, \"batter\": [ { \"id\": 3 } ] }");
+        assert_eq!(captured(json, "$.batters", JsonQueryMode::Key), "\"batters\"");
+        assert_eq!(captured(json, "$.batters.batter[0].id", JsonQueryMode::Key), "\"id\"");
+        assert_eq!(captured(json, "$.batters.batter[0]", JsonQueryMode::Key), "{");
+        assert_eq!(captured(json, "$.batters.batter[0].*", JsonQueryMode::Key), "\"id\"");
+        assert_eq!(captured("[1, \"a\"]", "$[*]", JsonQueryMode::Key), "1\"a\"");
+        assert_eq!(captured("1", "$", JsonQueryMode::Key), "1");
+    }
+
+    #[test]
+    fn test_escaped_names() {
+        let json = "{ \"say \\\"hi\\\"\": 1, \"caf\\u00e9\": 2 }";
 
-            self.document_array_cursors.push(-1);
+        assert_eq!(captured(json, "$['say \"hi\"']", JsonQueryMode::Value), " 1");
+        assert_eq!(captured(json, "$.café", JsonQueryMode::Value), " 2 ");
+    }
+
+    #[test]
+    fn test_index_after_nested_containers() {
+        let json = "{ \"items\": [ { \"id\": 0, \"tags\": [ \"a\" ], \"size\": { \"w\": 1 } }, { \"id\": 1 } ] }";
+
+        assert_eq!(captured(json, "$.items[1].id", JsonQueryMode::Value), " 1 ");
+    }
+
+    /// Picks out the indexes of a slice the way RFC 9535 describes it, stepping through the array from one bound to the other.
+    fn slice(len: isize, start: Option<isize>, end: Option<isize>, step: isize) -> Vec<isize> {
+        let normalize = |bound: isize| if bound >= 0 { bound } else { len + bound };
+        let mut indexes = Vec::new();
+
+        if step > 0 {
+            let lower = normalize(start.unwrap_or(0)).clamp(0, len);
+            let upper = normalize(end.unwrap_or(len)).clamp(0, len);
+            let mut i = lower;
+
+            while i < upper {
+                indexes.push(i);
+                i += step;
+            }
+        } else if step < 0 {
+            let upper = normalize(start.unwrap_or(len - 1)).clamp(-1, len - 1);
+            let lower = normalize(end.unwrap_or(-len - 1)).clamp(-1, len - 1);
+            let mut i = upper;
+
+            while lower < i {
+                indexes.push(i);
+                i += step;
+            }
+
+            indexes.reverse();
         }
 
-        if !self.path_aligned {
-            return;
+        indexes
+    }
+
+    #[test]
+    fn test_array_slices() {
+        let bounds = [None, Some(-5), Some(-2), Some(-1), Some(0), Some(1), Some(3), Some(7)];
+
+        for len in 0..6 {
+            let items: Vec<String> = (0..len).map(|i| i.to_string()).collect();
+            let json = format!("{{ \"a\": [{}] }}", items.join(","));
+
+            for start in bounds {
+                for end in bounds {
+                    for step in [-3, -1, 1, 2, 3] {
+                        let format_bound = |bound: Option<isize>| bound.map(|bound| bound.to_string()).unwrap_or_default();
+                        let path = format!("$.a[{}:{}:{}]", format_bound(start), format_bound(end), step);
+                        let expected: String = slice(len, start, end, step).iter().map(|i| i.to_string()).collect();
+
+                        assert_eq!(captured(json.as_str(), path.as_str(), JsonQueryMode::Value), expected, "{} of {}", path, json);
+                    }
+                }
+            }
         }
+    }
 
-        if !self.is_array_root() && self.path_cursor == self.path.operations().len() - 1 {
-            return;
+    #[test]
+    fn test_array_slice_formatting() {
+        let sample = include_str!("../../sample.json");
+        let toppings = [
+            "\n\t\t\t\t{ \"id\": \"5002\", \"type\": \"Glazed\" }",
+            "\n\t\t\t\t{ \"id\": \"5005\", \"type\": \"Sugar\" }",
+            "\n\t\t\t\t{ \"id\": \"5007\", \"type\": \"Powdered Sugar\" }",
+        ];
+
+        assert_eq!(captured(sample, "$.toppings.topping[1:4]", JsonQueryMode::Value), toppings.concat());
+        assert_eq!(captured(sample, "$.toppings.topping[1:4].type", JsonQueryMode::Value), " \"Glazed\"  \"Sugar\"  \"Powdered Sugar\" ");
+        assert_eq!(captured(sample, "$.toppings.topping[:2].id", JsonQueryMode::Key), "\"id\"\"id\"");
+    }
+
+    #[test]
+    fn test_array_slice_window() {
+        let json_path = JsonPath::from("$.a[-2:]");
+        let mut query = JsonQuery::from(&json_path);
+        let mut json_lexer = JsonStreamLexer::new();
+        let mut released = String::new();
+
+        // Items more than two from the end are let go as soon as the third item after them starts.
+        for c in "{ \"a\": [ 0, 1, 2, 3, 4 ".chars() {
+            json_lexer.push_char(c).unwrap();
+
+            while let JsonStreamStatus::Token(token) = json_lexer.pop_token() {
+                query.push_token(token);
+            }
+
+            while let JsonQueryStatus::Token { token, capture, .. } = query.pop_token() {
+                assert!(!capture);
+                released.push_str(token.raw());
+            }
         }
 
-        match &self.path.operations()[self.path_cursor] {
-            JsonPathOperator::ObjectRoot => self.path_cursor += 1,
-            JsonPathOperator::ArrayRoot(_) => {}
-            JsonPathOperator::MemberAccess(_) => self.path_cursor += 1,
-            JsonPathOperator::DeepScanMemberAccess(_) => todo!(),
-            JsonPathOperator::ArrayIndex(_) => self.path_cursor += 1,
-            JsonPathOperator::ArraySlice(_, _) => todo!(),
-            JsonPathOperator::FilterExpression(_) => todo!(),
+        assert_eq!(released, "{ \"a\": [ 0, 1, 2,");
+    }
+
+    #[test]
+    fn test_array_slice_window_negative_step() {
+        // Each item is decided as soon as it starts when nothing counts back from the end, and otherwise only the items
+        // the bounds count back over are held back.
+        for (path, held_back) in [("$.a[::-1]", 0), ("$.a[:-3:-1]", 2), ("$.a[-2::-1]", 1), ("$.a[5::-2]", 5)] {
+            let json_path = JsonPath::from(path);
+            let mut query = JsonQuery::from(&json_path);
+            let mut json_lexer = JsonStreamLexer::new();
+            let mut most_held_back = 0;
+            let mut pushed = 0;
+            let mut released = 0;
+
+            for i in 0..10000 {
+                for c in format!("{}{}", if i == 0 { "{ \"a\": [ " } else { ", " }, i).chars() {
+                    json_lexer.push_char(c).unwrap();
+
+                    while let JsonStreamStatus::Token(token) = json_lexer.pop_token() {
+                        pushed += matches!(token, JsonToken::IntegerValue { raw: _, value: _ }) as usize;
+                        query.push_token(token);
+                    }
+
+                    while let JsonQueryStatus::Token { token, .. } = query.pop_token() {
+                        released += matches!(token, JsonToken::IntegerValue { raw: _, value: _ }) as usize;
+                    }
+
+                    most_held_back = most_held_back.max(pushed - released);
+                }
+            }
+
+            assert!(most_held_back <= held_back + 1, "{} held back {} items", path, most_held_back);
         }
     }
 
-    fn recede(&mut self) {
-        if self.path_cursor != self.document_cursor {
-            self.document_cursor -= 1;
-            self.document_array_cursors.pop();
-            return;
-        } else {
-            self.path_cursor -= 1;
-            self.document_cursor -= 1;
-            self.document_array_cursors.pop();
-            self.path_aligned = true;
-            self.path_match = false;
+    #[test]
+    fn test_array_root() {
+        let sample = include_str!("../../sample2.json");
+
+        assert_eq!(captured(sample, "$[0].id", JsonQueryMode::Value), " \"5001\"");
+        assert_eq!(captured(sample, "$.[0].id", JsonQueryMode::Value), " \"5001\"");
+        assert_eq!(captured(sample, "$[-1]['type']", JsonQueryMode::Value), " \"Maple\" ");
+        assert_eq!(captured(sample, "$[1:3].id", JsonQueryMode::Value), " \"5002\" \"5005\"");
+        assert_eq!(captured(sample, "$[?@.type == 'Sugar'].id", JsonQueryMode::Value), " \"5005\"");
+        assert_eq!(captured(sample, "$[7]", JsonQueryMode::Value), "");
+        assert_eq!(captured(sample, "$", JsonQueryMode::Value), sample.trim_end());
+    }
+
+    #[test]
+    fn test_scalar_root() {
+        assert_eq!(captured(" 42 ", "$", JsonQueryMode::Value), " 42 ");
+        assert_eq!(captured("\"a\"", "$", JsonQueryMode::Value), "\"a\"");
+        assert_eq!(captured(" 42 ", "$[0]", JsonQueryMode::Value), "");
+        assert_eq!(captured(" 42 ", "$.a", JsonQueryMode::Value), "");
+    }
+
+    #[test]
+    fn test_wildcards() {
+        let sample = include_str!("../../sample.json");
+
+        assert_eq!(captured(sample, "$.batters.batter[*].id", JsonQueryMode::Value), " \"1001\" \"1002\" \"1003\" \"1004\"");
+        assert_eq!(captured(sample, "$.style.*", JsonQueryMode::Value), " \"hole\" \"filled\" ");
+        assert_eq!(captured(sample, "$.toppings.*[1]", JsonQueryMode::Value), "\n\t\t\t\t{ \"id\": \"5002\", \"type\": \"Glazed\" }");
+        assert_eq!(captured(sample, "$.batters.batter[0].*", JsonQueryMode::Key), "\"id\"\"type\"");
+
+        let json = "{ \"a\": { \"b\": [ 1, { \"c\": 2 } ] } }";
+
+        assert_eq!(captured(json, "$..*", JsonQueryMode::Value), " { \"b\": [ 1, { \"c\": 2 } ] }");
+        assert_eq!(captured(json, "$.a..[*]", JsonQueryMode::Key), "\"b\"1{\"c\"");
+    }
+
+    #[test]
+    fn test_unions() {
+        let sample = include_str!("../../sample.json");
+
+        assert_eq!(captured(sample, "$.batters.batter[2,0].id", JsonQueryMode::Value), " \"1001\" \"1003\"");
+        assert_eq!(captured(sample, "$['name','id']", JsonQueryMode::Value), " \"0001\" \"Cake\"");
+        assert_eq!(captured(sample, "$.toppings.topping[1:3, 2, ?@.id == '5004'].id", JsonQueryMode::Value), " \"5002\" \"5005\" \"5004\"");
+        assert_eq!(captured(sample, "$['type', 'ppu']", JsonQueryMode::Key), "\"type\"\"ppu\"");
+    }
+
+    #[test]
+    fn test_filters() {
+        let sample = include_str!("../../sample.json");
+
+        assert_eq!(captured(sample, "$.toppings.topping[?(@.type == 'Chocolate')].id", JsonQueryMode::Value), " \"5003\"");
+        assert_eq!(captured(sample, "$.style[?(@ != 'hole')]", JsonQueryMode::Value), " \"filled\" ");
+        assert_eq!(
+            captured(sample, "$.batters.batter[?@.id >= '1003' && !(@.type == 'Blueberry')].type", JsonQueryMode::Value),
+            " \"Devil's Food\" "
+        );
+        assert_eq!(captured(sample, "$.batters[?(@[1].type == 'Chocolate')]", JsonQueryMode::Key), "\"batter\"");
+
+        let json = "{ \"a\": [ 1, { \"b\": [ 2, 3 ] }, { \"c\": null }, 4 ] }";
+
+        assert_eq!(captured(json, "$.a[?(@ > 1)]", JsonQueryMode::Value), " 4 ");
+        assert_eq!(captured(json, "$.a[?(@.b[?(@ == 3)])]", JsonQueryMode::Value), " { \"b\": [ 2, 3 ] }");
+        assert_eq!(captured(json, "$.a[?(@.c == null)]", JsonQueryMode::Value), " { \"c\": null }");
+        assert_eq!(captured(json, "$.a[?(@.d == @.e)]", JsonQueryMode::Value), " 1 { \"b\": [ 2, 3 ] } { \"c\": null } 4 ");
+
+        let json = "[ 123456789012345678901234567890, 123456789012345678901234567891, 1e30 ]";
+
+        assert_eq!(captured(json, "$[?(@ == 123456789012345678901234567890)]", JsonQueryMode::Value), " 123456789012345678901234567890");
+        assert_eq!(
+            captured(json, "$[?(@ > 123456789012345678901234567890)]", JsonQueryMode::Value),
+            " 123456789012345678901234567891 1e30 "
+        );
+    }
+
+    #[test]
+    fn test_selections_reused() {
+        for (path, matched) in [("$[?(@.a == 7)].a", " 7 "), ("$[-2].a", " 9998 "), ("$[?(@.a > 9998)].a", " 9999 "), ("$[0,?(@.a == 5)].a", " 0  5 ")] {
+            let json_path = JsonPath::from(path);
+            let mut query = JsonQuery::from(&json_path);
+            let mut json_lexer = JsonStreamLexer::new();
+            let mut captured = String::new();
+            let mut most_selections = 0;
+
+            let json: String = (0..10000)
+                .map(|i| format!("{}{{ \"a\": {} }}", if i == 0 { "[ " } else { ", " }, i))
+                .chain([String::from(" ]")])
+                .collect();
+
+            for c in json.chars() {
+                json_lexer.push_char(c).unwrap();
+
+                while let JsonStreamStatus::Token(token) = json_lexer.pop_token() {
+                    query.push_token(token);
+                }
+
+                while let JsonQueryStatus::Token { token, capture, .. } = query.pop_token() {
+                    if capture {
+                        captured.push_str(token.raw());
+                    }
+                }
+
+                most_selections = most_selections.max(query.path.selections.values.len());
+            }
+
+            json_lexer.close().unwrap();
+            query.close();
+
+            while let JsonQueryStatus::Token { token, capture, .. } = query.pop_token() {
+                if capture {
+                    captured.push_str(token.raw());
+                }
+            }
+
+            assert_eq!(captured, matched, "{}", path);
+            assert!(most_selections <= 32, "{} kept {} selections", path, most_selections);
         }
     }
 
-    fn member_access(&mut self, name: &String) {
-        if self.path_cursor != self.document_cursor {
-            return;
+    #[test]
+    fn test_filter_decided_early() {
+        let json_path = JsonPath::from("$.a[?(@.id == 1)]");
+        let mut query = JsonQuery::from(&json_path);
+        let mut json_lexer = JsonStreamLexer::new();
+        let mut captured = String::new();
+
+        // The item is let go as soon as its id has gone past, without waiting for the rest of it.
+        for c in "{ \"a\": [ { \"id\": 1, \"more\": [ ".chars() {
+            json_lexer.push_char(c).unwrap();
+
+            while let JsonStreamStatus::Token(token) = json_lexer.pop_token() {
+                query.push_token(token);
+            }
+
+            while let JsonQueryStatus::Token { token, capture, .. } = query.pop_token() {
+                if capture {
+                    captured.push_str(token.raw());
+                }
+            }
         }
 
-        match &self.path.operations()[self.path_cursor] {
-            JsonPathOperator::ObjectRoot => self.path_aligned = false,
-            JsonPathOperator::ArrayRoot(_) => self.path_aligned = false,
-            JsonPathOperator::MemberAccess(path_member) => self.path_aligned = *name == *path_member,
-            JsonPathOperator::DeepScanMemberAccess(_) => self.path_aligned = false,
-            JsonPathOperator::ArrayIndex(_) => self.path_aligned = false,
-            JsonPathOperator::ArraySlice(_, _) => self.path_aligned = false,
-            JsonPathOperator::FilterExpression(_) => self.path_aligned = false,
+        assert_eq!(captured, " { \"id\": 1, \"more\": [");
+    }
+
+    #[test]
+    fn test_negative_indices() {
+        let sample = include_str!("../../sample.json");
+
+        assert_eq!(captured(sample, "$.toppings.topping[-1].id", JsonQueryMode::Value), " \"5004\"");
+        assert_eq!(captured(sample, "$.toppings.topping[-3].id", JsonQueryMode::Value), " \"5006\"");
+        assert_eq!(captured(sample, "$.toppings.topping[-7].id", JsonQueryMode::Value), " \"5001\"");
+        assert_eq!(captured(sample, "$.toppings.topping[-8].id", JsonQueryMode::Value), "");
+        assert_eq!(captured(sample, "$.batters.batter[-1,0].id", JsonQueryMode::Value), " \"1001\" \"1004\"");
+        assert_eq!(captured("{ \"a\": [ ] }", "$.a[-1]", JsonQueryMode::Value), "");
+        assert_eq!(captured("{ \"a\": [ 1, [ 2, 3 ] ] }", "$.a[-1][-2]", JsonQueryMode::Value), " 2");
+    }
+
+    #[test]
+    fn test_negative_index_window() {
+        let json_path = JsonPath::from("$.a[-2]");
+        let mut query = JsonQuery::from(&json_path);
+        let mut json_lexer = JsonStreamLexer::new();
+        let mut released = String::new();
+
+        // Only the last two items are held back, everything before them is let go as the array goes past.
+        for c in "{ \"a\": [ 0, 1, 2, 3, 4 ".chars() {
+            json_lexer.push_char(c).unwrap();
+
+            while let JsonStreamStatus::Token(token) = json_lexer.pop_token() {
+                query.push_token(token);
+            }
+
+            while let JsonQueryStatus::Token { token, capture, .. } = query.pop_token() {
+                assert!(!capture);
+                released.push_str(token.raw());
+            }
         }
 
-        self.path_match = self.path_aligned && self.path_cursor == self.path.operations().len() - 1;
+        assert_eq!(released, "{ \"a\": [ 0, 1, 2,");
     }
 
-    fn increment_index(&mut self) {
-        if !self.is_array_root() && self.path_cursor != self.document_cursor {
-            return;
+    #[test]
+    fn test_deep_scan() {
+        let sample = include_str!("../../sample.json");
+
+        assert_eq!(captured(sample, "$..batter[1].type", JsonQueryMode::Value), " \"Chocolate\" ");
+        assert_eq!(captured(sample, "$..['topping'][-1:].id", JsonQueryMode::Value), " \"5004\"");
+        assert_eq!(captured(sample, "$..id", JsonQueryMode::Key).matches("\"id\"").count(), 12);
+
+        let json = "{ \"a\": [ 1, [ 2, 3 ], { \"b\": [ 4 ] } ], \"c\": [ 5, 6 ] }";
+
+        assert_eq!(captured(json, "$..[0]", JsonQueryMode::Value), " 1 2 4  5");
+        assert_eq!(captured(json, "$..[-1]", JsonQueryMode::Value), " 3  { \"b\": [ 4 ] } 6 ");
+        assert_eq!(captured(json, "$..[0,1]", JsonQueryMode::Value), " 1 [ 2, 3 ] 4  5 6 ");
+        assert_eq!(captured(json, "$..['b','c'][0]", JsonQueryMode::Value), " 4  5");
+        assert_eq!(captured(json, "$..[?(@ > 3)]", JsonQueryMode::Value), " 4  5 6 ");
+    }
+
+    #[test]
+    fn test_deep_scan_nested_matches() {
+        let json = "{ \"db\": { \"password\": \"a\", \"replica\": [ { \"password\": \"b\" } ] }, \"password\": { \"password\": \"c\" } }";
+
+        assert_eq!(captured(json, "$..password", JsonQueryMode::Key), "\"password\"\"password\"\"password\"\"password\"");
+        assert_eq!(captured(json, "$..password", JsonQueryMode::Value), " \"a\" \"b\"  { \"password\": \"c\" }");
+        assert_eq!(captured(json, "$..replica..password", JsonQueryMode::Value), " \"b\" ");
+        assert_eq!(captured(json, "$.db..password", JsonQueryMode::Value), " \"a\" \"b\" ");
+    }
+
+    #[test]
+    fn test_match_boundaries() {
+        let json_path = JsonPath::from("$..a");
+        let mut query = JsonQuery::from(&json_path);
+        let mut json_lexer = JsonStreamLexer::new();
+        let mut boundaries = Vec::new();
+
+        for c in "{\"a\":[1,{\"a\":2}]}".chars() {
+            json_lexer.push_char(c).unwrap();
+
+            while let JsonStreamStatus::Token(token) = json_lexer.pop_token() {
+                query.push_token(token);
+            }
+        }
+
+        json_lexer.close().unwrap();
+        query.close();
+
+        while let JsonQueryStatus::Token { token, capture, boundary, .. } = query.pop_token() {
+            if capture {
+                boundaries.push((String::from(token.raw()), boundary));
+            }
         }
 
-        match &self.path.operations()[self.path_cursor] {
-            JsonPathOperator::ObjectRoot => self.path_aligned = false,
-            JsonPathOperator::ArrayRoot(path_index) => {
-                if let Some(document_array_cursor) = self.document_array_cursors.last_mut() {
-                    *document_array_cursor += 1;
-                    self.path_aligned = *document_array_cursor == *path_index;
-                } else {
-                    self.path_aligned = false
+        assert_eq!(
+            boundaries,
+            vec![
+                (String::from("["), JsonMatchBoundary::Start),
+                (String::from("1"), JsonMatchBoundary::None),
+                (String::from(","), JsonMatchBoundary::None),
+                (String::from("{"), JsonMatchBoundary::None),
+                (String::from("\"a\""), JsonMatchBoundary::None),
+                (String::from(":"), JsonMatchBoundary::None),
+                (String::from("2"), JsonMatchBoundary::Whole),
+                (String::from("}"), JsonMatchBoundary::None),
+                (String::from("]"), JsonMatchBoundary::End),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_match_paths() {
+        let paths = |json: &str, path: &str, mode: JsonQueryMode| {
+            let json_path = JsonPath::from(path);
+            let mut query = JsonQuery::with_mode(&json_path, mode);
+            let mut json_lexer = JsonStreamLexer::new();
+            let mut paths = Vec::new();
+
+            for c in json.chars() {
+                json_lexer.push_char(c).unwrap();
+
+                while let JsonStreamStatus::Token(token) = json_lexer.pop_token() {
+                    query.push_token(token);
                 }
             }
-            JsonPathOperator::MemberAccess(_) => self.path_aligned = false,
-            JsonPathOperator::DeepScanMemberAccess(_) => self.path_aligned = false,
-            JsonPathOperator::ArrayIndex(path_index) => {
-                if let Some(document_array_cursor) = self.document_array_cursors.last_mut() {
-                    *document_array_cursor += 1;
-                    self.path_aligned = *document_array_cursor == *path_index;
-                } else {
-                    self.path_aligned = false
+
+            json_lexer.close().unwrap();
+
+            while let JsonStreamStatus::Token(token) = json_lexer.pop_token() {
+                query.push_token(token);
+            }
+
+            query.close();
+
+            while let JsonQueryStatus::Token { path, .. } = query.pop_token() {
+                paths.extend(path);
+            }
+
+            paths
+        };
+
+        let json = "{ \"a\": [ 1, { \"it's\": 2, \"b\\\\c\\n\": [ 3 ] } ] }";
+
+        assert_eq!(paths(json, "$", JsonQueryMode::Value), vec!["$"]);
+        assert_eq!(paths(json, "$.a[-1:]", JsonQueryMode::Value), vec!["$['a'][1]"]);
+        assert_eq!(paths(json, "$.a[1].*", JsonQueryMode::Value), vec!["$['a'][1]['it\\'s']", "$['a'][1]['b\\\\c\\n']"]);
+        assert!(paths(json, "$.c", JsonQueryMode::Value).is_empty());
+        assert_eq!(paths(json, "$.a[1]['it\\'s']", JsonQueryMode::Key), vec!["$['a'][1]['it\\'s']"]);
+    }
+
+    #[test]
+    fn test_json_pointers() {
+        let json = "{ \"a\": [ 1, { \"b/c\": 2, \"d~e\": 3 } ], \"1\": { \"0\": 4 }, \"\": 5 }";
+
+        assert_eq!(captured_by(json, &JsonPath::parse_pointer("/a/1/b~1c").unwrap(), JsonQueryMode::Value), " 2");
+        assert_eq!(captured_by(json, &JsonPath::parse_pointer("/a/1/d~0e").unwrap(), JsonQueryMode::Value), " 3 ");
+        assert_eq!(captured_by(json, &JsonPath::parse_pointer("/1/0").unwrap(), JsonQueryMode::Value), " 4 ");
+        assert_eq!(captured_by(json, &JsonPath::parse_pointer("/").unwrap(), JsonQueryMode::Value), " 5 ");
+        assert_eq!(captured_by(json, &JsonPath::parse_pointer("/a/-").unwrap(), JsonQueryMode::Value), "");
+        assert_eq!(captured_by(json, &JsonPath::parse_pointer("").unwrap(), JsonQueryMode::Value), json);
+    }
+
+    /// A case from a JSONPath compliance suite, laid out the same way as the cases in the suite's `cts.json`.
+    struct ComplianceCase {
+        name: String,
+        selector: String,
+        document: JsonValue,
+        results: Vec<JsonValue>,
+        is_invalid: bool,
+    }
+
+    fn values(json: &str) -> Vec<JsonValue> {
+        let mut json_lexer = JsonStreamLexer::new();
+        let mut builder = JsonValueBuilder::new();
+        let mut values = Vec::new();
+
+        for c in json.chars() {
+            json_lexer.push_char(c).unwrap();
+        }
+
+        json_lexer.close().unwrap();
+
+        while let JsonStreamStatus::Token(token) = json_lexer.pop_token() {
+            values.extend(builder.push_token(&token));
+        }
+
+        values
+    }
+
+    fn compliance_cases(json: &str) -> Vec<ComplianceCase> {
+        let member = |value: &JsonValue, name: &str| match value {
+            JsonValue::Object(members) => members.iter().find(|(member, _)| member == name).map(|(_, value)| value.clone()),
+            _ => None,
+        };
+        let text = |value: Option<JsonValue>| match value {
+            Some(JsonValue::String(text)) => text,
+            _ => String::new(),
+        };
+
+        let tests = match values(json).first().and_then(|suite| member(suite, "tests")) {
+            Some(JsonValue::Array(tests)) => tests,
+            _ => Vec::new(),
+        };
+
+        tests
+            .iter()
+            .map(|test| ComplianceCase {
+                name: text(member(test, "name")),
+                selector: text(member(test, "selector")),
+                document: member(test, "document").unwrap_or(JsonValue::Null),
+                // A case whose results can come out in more than one order lists each of them under `results`.
+                results: match (member(test, "result"), member(test, "results")) {
+                    (Some(result), _) => vec![result],
+                    (None, Some(JsonValue::Array(results))) => results,
+                    _ => Vec::new(),
+                },
+                is_invalid: member(test, "invalid_selector") == Some(JsonValue::Boolean(true)),
+            })
+            .collect()
+    }
+
+    /// Runs a case through `JsonPath::parse` and `JsonQuery`. An invalid selector passes if it is rejected, either as an
+    /// error or as non-standard, and a valid one if the values it matches are one of the expected results.
+    fn passes(case: &ComplianceCase) -> bool {
+        let json_path = match JsonPath::parse(case.selector.as_str()) {
+            Ok(json_path) => json_path,
+            Err(_) => return case.is_invalid,
+        };
+
+        if case.is_invalid || json_path.non_standard().is_some() {
+            return case.is_invalid && json_path.non_standard().is_some();
+        }
+
+        let mut query = JsonQuery::from(&json_path);
+        let mut json_lexer = JsonStreamLexer::new();
+        let mut matched = Vec::new();
+        let mut builders: Vec<(usize, JsonValueBuilder)> = Vec::new();
+
+        for c in case.document.to_string().chars() {
+            json_lexer.push_char(c).unwrap();
+
+            while let JsonStreamStatus::Token(token) = json_lexer.pop_token() {
+                query.push_token(token);
+            }
+        }
+
+        json_lexer.close().unwrap();
+
+        while let JsonStreamStatus::Token(token) = json_lexer.pop_token() {
+            query.push_token(token);
+        }
+
+        query.close();
+
+        // Each match is built up separately, including those nested in other matches, and they're kept in the order they start.
+        while let JsonQueryStatus::Token { token, capture, boundary, .. } = query.pop_token() {
+            if !capture {
+                continue;
+            }
+
+            if matches!(boundary, JsonMatchBoundary::Start | JsonMatchBoundary::Whole) {
+                builders.push((matched.len(), JsonValueBuilder::new()));
+                matched.push(JsonValue::Null);
+            }
+
+            for (i, builder) in builders.iter_mut() {
+                if let Some(value) = builder.push_token(&token) {
+                    matched[*i] = value;
                 }
             }
-            JsonPathOperator::ArraySlice(_, _) => self.path_aligned = false,
-            JsonPathOperator::FilterExpression(_) => self.path_aligned = false,
+
+            if matches!(boundary, JsonMatchBoundary::End | JsonMatchBoundary::Whole) {
+                builders.pop();
+            }
         }
 
-        self.path_match = self.path_aligned && self.path_cursor == self.path.operations().len() - 1;
+        case.results.contains(&JsonValue::Array(matched))
     }
 
-    fn is_matching(&self) -> bool {
-        self.path_match
+    /// Runs every suite in `tests/jsonpath`. The cases that are known to fail are listed in `known-failures.txt`, so this
+    /// fails if a case starts failing or if a known failure starts passing and can be taken off the list.
+    #[test]
+    fn test_jsonpath_compliance() {
+        let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("jsonpath");
+        let known_failures = fs::read_to_string(directory.join("known-failures.txt")).unwrap();
+        let known_failures: Vec<&str> = known_failures.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')).collect();
+
+        let mut suites: Vec<_> = fs::read_dir(&directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == "json"))
+            .collect();
+        suites.sort();
+
+        let mut unexpected = Vec::new();
+
+        for suite in suites {
+            for case in compliance_cases(&fs::read_to_string(&suite).unwrap()) {
+                match (passes(&case), known_failures.contains(&case.name.as_str())) {
+                    (true, true) => unexpected.push(format!("'{}' passes now and can be taken off the known failures", case.name)),
+                    (false, false) => unexpected.push(format!("'{}' ({}) fails", case.name, case.selector)),
+                    _ => {}
+                }
+            }
+        }
+
+        assert!(unexpected.is_empty(), "{}", unexpected.join("\n"));
     }
 }
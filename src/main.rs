@@ -1,23 +1,57 @@
 mod json;
+mod utf8;
 mod yaml;
 
-use clap::Parser;
-use std::io::{self, Read};
+use clap::{ArgGroup, Parser};
+use std::collections::VecDeque;
+use std::env;
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions, Permissions};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
 
 use json::{
-    lexer::{JsonStreamLexer, JsonStreamStatus, JsonToken},
+    editor::{JsonEdit, JsonStreamEditor},
+    filter::JsonValue,
+    lexer::{JsonPosition, JsonStreamLexer, JsonStreamStatus, JsonToken},
     path::JsonPath,
-    query::JsonQuery,
+    query::{JsonMatchBoundary, JsonQuery, JsonQueryStatus},
 };
 
+use utf8::Utf8Reader;
 use yaml::lexer::{YamlStreamLexer, YamlStreamStatus, YamlToken};
 
 #[derive(Parser, Debug)]
 #[command(help_template = "ssedit {version}\n{author-with-newline}https://github.com/chris-tomich/ssedit\n {about-section} {usage-heading} {usage} \n {all-args} {tab}")]
 #[command(author, version, about)]
+#[command(group(ArgGroup::new("operation").required(true).args(["query", "set", "delete", "insert", "append", "prepend", "rename"])))]
 struct SSEditArgs {
     #[arg(short, long, help = "the elements to query using JSON path")]
-    query: String,
+    query: Option<String>,
+
+    #[arg(long, value_name = "PATH=VALUE", help = "replaces the elements at the JSON path with a JSON value, leaving all other formatting untouched")]
+    set: Option<String>,
+
+    #[arg(long, value_name = "PATH", help = "removes the elements at the JSON path along with their delimiters, whitespace and new lines")]
+    delete: Option<String>,
+
+    #[arg(
+        long,
+        num_args = 2,
+        value_names = ["PATH", "KEY=VALUE"],
+        help = "adds a member to the objects at the JSON path, laid out the same way as the members around it"
+    )]
+    insert: Option<Vec<String>>,
+
+    #[arg(long, num_args = 2, value_names = ["PATH", "VALUE"], help = "adds a JSON value after the last item of the arrays at the JSON path")]
+    append: Option<Vec<String>>,
+
+    #[arg(long, num_args = 2, value_names = ["PATH", "VALUE"], help = "adds a JSON value before the first item of the arrays at the JSON path")]
+    prepend: Option<Vec<String>>,
+
+    #[arg(long, num_args = 2, value_names = ["PATH", "NEWNAME"], help = "renames the members at the JSON path, leaving their values untouched")]
+    rename: Option<Vec<String>>,
 
     #[arg(
         short = 's',
@@ -27,6 +61,18 @@ struct SSEditArgs {
     )]
     raw_symbols: bool,
 
+    #[arg(
+        short = 'r',
+        long,
+        default_value_t = false,
+        conflicts_with_all = ["json_output", "raw_symbols", "json_array"],
+        help = "writes strings decoded without their quotes and other values as they appear in the input, which is the default"
+    )]
+    raw_output: bool,
+
+    #[arg(long, default_value_t = false, help = "writes each match as JSON, with strings quoted and escaped exactly as they appear in the input")]
+    json_output: bool,
+
     #[arg(
         short = 'f',
         long,
@@ -34,194 +80,694 @@ struct SSEditArgs {
         help = String::from("file type to be edited i.e. json or yaml"),
     )]
     file_type: String,
+
+    #[arg(
+        short = 'i',
+        long,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "",
+        value_name = "SUFFIX",
+        help = "edits the files in place, keeping a copy of each original with SUFFIX appended to its name if one is given"
+    )]
+    in_place: Option<String>,
+
+    #[arg(
+        short = '0',
+        long,
+        default_value_t = false,
+        help = "separates matches with a NUL character rather than a new line, for matches that can span several lines"
+    )]
+    null: bool,
+
+    #[arg(long, default_value_t = false, conflicts_with = "null", help = "writes the matches out as a JSON array")]
+    json_array: bool,
+
+    #[arg(long, default_value_t = false, help = "writes the normalized path of each match before it, such as $['batters']['batter'][1]['type']")]
+    with_path: bool,
+
+    #[arg(long, default_value_t = false, help = "writes where each match starts before it as FILE:LINE:COLUMN, with '-' as the file for stdin")]
+    with_location: bool,
+
+    #[arg(
+        long,
+        default_value_t = false,
+        conflicts_with_all = ["exists", "value_type", "null", "json_array", "with_path", "with_location"],
+        help = "writes out how many matches there are in each file rather than the matches themselves"
+    )]
+    count: bool,
+
+    #[arg(
+        long,
+        default_value_t = false,
+        conflicts_with_all = ["value_type", "null", "json_array", "with_path", "with_location"],
+        help = "writes nothing and exits with a status of 0 if there are any matches and 1 if there aren't"
+    )]
+    exists: bool,
+
+    #[arg(
+        long = "type",
+        default_value_t = false,
+        help = "writes the type of each match, object, array, string, number, boolean or null, rather than the match"
+    )]
+    value_type: bool,
+
+    #[arg(long, default_value_t = false, help = "rejects JSON paths that use syntax RFC 9535 doesn't allow, such as '$.[0]'")]
+    strict_jsonpath: bool,
+
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "reads paths as RFC 6901 JSON pointers such as '/batters/batter/1/type', which is the default for paths starting with '/'"
+    )]
+    pointer: bool,
+
+    #[arg(value_name = "FILE", help = "the files to read, by default the input is read from stdin")]
+    files: Vec<PathBuf>,
 }
 
-fn main() -> io::Result<()> {
-    let args = SSEditArgs::parse();
+fn main() {
+    let args = SSEditArgs::parse_from(env::args_os().map(expand_in_place_suffix));
+
+    if args.in_place.is_some() && (args.files.is_empty() || args.query.is_some()) {
+        eprintln!("ssedit: -i/--in-place needs an editing operation and at least one file to edit");
+        process::exit(1);
+    }
+
+    let mut output = BufWriter::new(io::stdout().lock());
+
+    let result = if args.files.is_empty() {
+        parse(&args, None, &mut io::stdin().lock(), &mut output)
+    } else {
+        args.files.iter().try_fold(0, |matches, file| {
+            let result = match &args.in_place {
+                Some(suffix) => edit_in_place(&args, file, suffix).map(|_| 0),
+                None => File::open(file).and_then(|mut input| parse(&args, Some(file), &mut input, &mut output)),
+            };
+
+            result.map(|found| matches + found).map_err(|err| io::Error::new(err.kind(), format!("{}: {}", file.display(), err)))
+        })
+    };
 
+    let result = result.and_then(|matches| output.flush().map(|_| matches));
+
+    match result {
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => {}
+        Err(err) => {
+            let _ = output.flush();
+            eprintln!("ssedit: {}", err);
+            process::exit(1);
+        }
+        Ok(0) if args.exists => process::exit(1),
+        Ok(_) => {}
+    }
+}
+
+/// Turns `-iSUFFIX` into `--in-place=SUFFIX` so a backup suffix can be given the same way as with `sed -i.bak`. The suffix
+/// can't be a separate argument as it would be mistaken for a file.
+fn expand_in_place_suffix(arg: OsString) -> OsString {
+    match arg.to_str() {
+        Some(arg) if arg.starts_with("-i") && arg.len() > 2 && !arg.starts_with("-i=") => OsString::from(format!("--in-place={}", &arg[2..])),
+        _ => arg,
+    }
+}
+
+/// Parses a document, returning how many matches a query found in it.
+fn parse(args: &SSEditArgs, file: Option<&Path>, input: &mut dyn Read, output: &mut dyn Write) -> io::Result<usize> {
     if args.file_type.eq_ignore_ascii_case("json") {
-        json_parse(args)
+        json_parse(args, file, input, output)
     } else if args.file_type.eq_ignore_ascii_case("yaml") {
-        yaml_parse(args)
+        yaml_parse(args, input, output).map(|_| 0)
     } else {
-        Ok(())
+        Ok(0)
     }
 }
 
-fn json_parse(args: SSEditArgs) -> io::Result<()> {
-    let mut buffer = [0; 1];
+/// Edits a file by writing the result to a temporary file in the same directory and then renaming it over the original.
+/// The original is only replaced once the whole document has been written out, so it is left untouched if anything fails
+/// partway through.
+fn edit_in_place(args: &SSEditArgs, file: &Path, suffix: &str) -> io::Result<()> {
+    let permissions = fs::metadata(file)?.permissions();
 
-    let query_path_str = if args.query.is_empty() {
-        eprintln!("no select command provided");
-        return Ok(());
-    } else {
-        args.query.as_str()
+    let mut temp_name = OsString::from(".");
+    temp_name.push(file.file_name().unwrap_or_default());
+    temp_name.push(format!(".ssedit-{}.tmp", process::id()));
+
+    let temp_file = file.with_file_name(temp_name);
+
+    if let Err(err) = write_in_place(args, file, &temp_file, permissions) {
+        let _ = fs::remove_file(&temp_file);
+        return Err(err);
+    }
+
+    if !suffix.is_empty() {
+        let mut backup_name = file.as_os_str().to_os_string();
+        backup_name.push(suffix);
+
+        fs::copy(file, backup_name)?;
+    }
+
+    fs::rename(&temp_file, file)
+}
+
+fn write_in_place(args: &SSEditArgs, file: &Path, temp_file: &Path, permissions: Permissions) -> io::Result<()> {
+    let mut input = File::open(file)?;
+    let output = OpenOptions::new().write(true).create_new(true).open(temp_file)?;
+
+    let mut writer = BufWriter::new(&output);
+
+    parse(args, Some(file), &mut input, &mut writer)?;
+
+    writer.flush()?;
+    drop(writer);
+
+    output.sync_all()?;
+    fs::set_permissions(temp_file, permissions)
+}
+
+fn invalid_input(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+/// Edits the document if an editing operation was given, otherwise queries it. Returns how many matches the query found,
+/// which is always none when editing.
+fn json_parse(args: &SSEditArgs, file: Option<&Path>, input: &mut dyn Read, output: &mut dyn Write) -> io::Result<usize> {
+    if let Some((path, edit)) = json_edit_operation(args)? {
+        json_edit(args, path, edit, input, output)?;
+        return Ok(0);
+    }
+
+    let query_path_str = match &args.query {
+        Some(query) if !query.is_empty() || args.pointer => query.as_str(),
+        _ => {
+            eprintln!("no select command provided");
+            return Ok(0);
+        }
     };
 
-    let query_path = JsonPath::from(query_path_str);
+    let query_path = json_path(args, query_path_str)?;
     let mut query = JsonQuery::from(&query_path);
 
-    let mut capture;
-
-    let mut captured_tokens = Vec::new();
-    let mut number_of_values = 0;
+    let mut records = JsonRecords::new(file);
 
     let mut json_lexer = JsonStreamLexer::new();
 
-    loop {
-        match io::stdin().lock().read(&mut buffer) {
-            Ok(0) => break,
-            Ok(_) => {
-                let c = buffer[0] as char;
+    let mut reader = Utf8Reader::new(input);
 
-                if let Err(msg) = json_lexer.push_char(c) {
-                    panic!("{}", msg);
-                }
+    while let Some(text) = reader.read_str()? {
+        for c in text.chars() {
+            json_lexer.push_char(c).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
 
-                loop {
-                    match json_lexer.pop_token() {
-                        JsonStreamStatus::None => break,
-                        JsonStreamStatus::Token(token) => {
-                            capture = query.parse(&token);
-
-                            if capture {
-                                if args.raw_symbols {
-                                    match token {
-                                        JsonToken::PropertyName { raw, name: _ } => print!("{}", raw),
-                                        JsonToken::BooleanValue { raw, value: _ } => print!("{}", raw),
-                                        JsonToken::StringValue { raw, value: _ } => print!("{}", raw),
-                                        JsonToken::IntegerValue { raw, value: _ } => print!("{}", raw),
-                                        JsonToken::FloatValue { raw, value: _ } => print!("{}", raw),
-                                        JsonToken::NullValue(raw) => print!("{}", raw),
-                                        JsonToken::UndefinedValue(raw) => print!("{}", raw),
-                                        JsonToken::ObjectOpen(raw) => print!("{}", raw),
-                                        JsonToken::ObjectClose(raw) => print!("{}", raw),
-                                        JsonToken::ArrayOpen(raw) => print!("{}", raw),
-                                        JsonToken::ArrayClose(raw) => print!("{}", raw),
-                                        JsonToken::Whitespace(raw) => print!("{}", raw),
-                                        JsonToken::NewLine(raw) => print!("{}", raw),
-                                        JsonToken::ArrayItemDelimiter(raw) => print!("{}", raw),
-                                        JsonToken::PropertyDelimiter(raw) => print!("{}", raw),
-                                        JsonToken::KeyValueDelimiter(raw) => print!("{}", raw),
-                                    }
-                                } else {
-                                    match token {
-                                        JsonToken::StringValue { raw: _, value: _ } => number_of_values += 1,
-                                        JsonToken::IntegerValue { raw: _, value: _ } => number_of_values += 1,
-                                        JsonToken::FloatValue { raw: _, value: _ } => number_of_values += 1,
-                                        _ => {}
-                                    }
-
-                                    captured_tokens.push(token);
-                                }
-                            }
-                        }
-                    }
-                }
+            while let Some((token, span)) = json_lexer.pop_token_with_span() {
+                query.push_token(token);
+                records.starts.push_back(span.start);
+            }
+
+            take_captures(args, &mut query, &mut records, output)?;
+
+            // Only whether there are any matches matters, so there's no need to read any further.
+            if args.exists && records.written > 0 {
+                return Ok(records.written);
             }
-            Err(_) => todo!(),
         }
     }
 
-    if !args.raw_symbols {
-        if number_of_values <= 1 {
-            for token in captured_tokens {
-                match token {
-                    JsonToken::StringValue { raw: _, value } => print!("{}", value),
-                    JsonToken::IntegerValue { raw: _, value } => print!("{}", value),
-                    JsonToken::FloatValue { raw: _, value } => print!("{}", value),
-                    _ => {}
-                }
+    json_lexer.close().map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+    while let Some((token, span)) = json_lexer.pop_token_with_span() {
+        query.push_token(token);
+        records.starts.push_back(span.start);
+    }
+
+    query.close();
+    take_captures(args, &mut query, &mut records, output)?;
+
+    if args.count {
+        writeln!(output, "{}", records.written)?;
+    }
+
+    if args.json_array {
+        let opening = if records.written == 0 { "[" } else { "" };
+        writeln!(output, "{}]", opening)?;
+    }
+
+    Ok(records.written)
+}
+
+/// Takes the tokens the query has decided on, writing out each match once all of it has gone past. When only the number
+/// of matches, whether there are any or their types are wanted, the first token of a match is all that's needed so none of
+/// them are held on to.
+fn take_captures(args: &SSEditArgs, query: &mut JsonQuery, records: &mut JsonRecords, output: &mut dyn Write) -> io::Result<()> {
+    while let JsonQueryStatus::Token { token, capture, boundary, path } = query.pop_token() {
+        let start = records.starts.pop_front().expect("the query hands back every token it is given");
+        let is_match = capture && matches!(boundary, JsonMatchBoundary::Start | JsonMatchBoundary::Whole);
+
+        if args.count || args.exists {
+            if is_match {
+                records.written += 1;
             }
-        } else {
-            for token in captured_tokens {
-                match token {
-                    JsonToken::PropertyName { raw, name: _ } => print!("{}", raw),
-                    JsonToken::BooleanValue { raw, value: _ } => print!("{}", raw),
-                    JsonToken::StringValue { raw, value: _ } => print!("{}", raw),
-                    JsonToken::IntegerValue { raw, value: _ } => print!("{}", raw),
-                    JsonToken::FloatValue { raw, value: _ } => print!("{}", raw),
-                    JsonToken::NullValue(raw) => print!("{}", raw),
-                    JsonToken::UndefinedValue(raw) => print!("{}", raw),
-                    JsonToken::ObjectOpen(raw) => print!("{}", raw),
-                    JsonToken::ObjectClose(raw) => print!("{}", raw),
-                    JsonToken::ArrayOpen(raw) => print!("{}", raw),
-                    JsonToken::ArrayClose(raw) => print!("{}", raw),
-                    JsonToken::Whitespace(raw) => print!("{}", raw),
-                    JsonToken::NewLine(raw) => print!("{}", raw),
-                    JsonToken::ArrayItemDelimiter(raw) => print!("{}", raw),
-                    JsonToken::PropertyDelimiter(raw) => print!("{}", raw),
-                    JsonToken::KeyValueDelimiter(raw) => print!("{}", raw),
-                }
+        } else if args.value_type {
+            if is_match {
+                let value = String::from(type_name(&token));
+                let record = JsonRecord {
+                    location: records.location(start),
+                    path,
+                    tokens: vec![JsonToken::StringValue { raw: format!("\"{}\"", value), value }],
+                    is_complete: true,
+                };
+
+                records.written += 1;
+                write_record(args, &record, records.written == 1, output)?;
+            }
+        } else if capture {
+            records.push_token(token, start, boundary, path);
+        }
+
+        while let Some(record) = records.pop_record() {
+            write_record(args, &record, records.written == 1, output)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes out a match as a record of its own, separated from the others by a new line, a NUL with `-0`, or a `,` inside
+/// an array with `--json-array`. A string on its own is written decoded unless JSON, the raw symbols or a JSON array are
+/// wanted. Anything else is written exactly as it appears in the input, which for numbers, booleans and nulls is the same
+/// as decoding them.
+///
+/// With `--with-location` and `--with-path` where the match starts and its path are written before it with a tab after
+/// each, which a normalized path never has in it, or in a JSON array each match becomes an object with them and the value.
+fn write_record(args: &SSEditArgs, record: &JsonRecord, is_first: bool, output: &mut dyn Write) -> io::Result<()> {
+    let is_object = args.json_array && (args.with_location || args.with_path);
+
+    if args.json_array {
+        write!(output, "{}", if is_first { "[" } else { "," })?;
+    }
+
+    if is_object {
+        write!(output, "{{")?;
+    }
+
+    if args.with_location && is_object {
+        write!(output, "\"location\":{},", JsonValue::String(record.location.clone()))?;
+    } else if args.with_location {
+        write!(output, "{}\t", record.location)?;
+    }
+
+    match (&record.path, is_object) {
+        (Some(path), true) if args.with_path => write!(output, "\"path\":{},", JsonValue::String(path.clone()))?,
+        (Some(path), false) if args.with_path => write!(output, "{}\t", path)?,
+        _ => {}
+    }
+
+    if is_object {
+        write!(output, "\"value\":")?;
+    }
+
+    match &record.tokens[..] {
+        [JsonToken::StringValue { raw: _, value }] if !args.json_output && !args.raw_symbols && !args.json_array => write!(output, "{}", value)?,
+        tokens => {
+            for token in tokens {
+                write!(output, "{}", token.raw())?;
+            }
+        }
+    }
+
+    match (args.json_array, args.null) {
+        (true, _) if is_object => write!(output, "}}"),
+        (true, _) => Ok(()),
+        (false, true) => write!(output, "\0"),
+        (false, false) => writeln!(output),
+    }
+}
+
+/// The JSON type of the value a match starts with.
+fn type_name(token: &JsonToken) -> &'static str {
+    match token {
+        JsonToken::ObjectOpen(_) => "object",
+        JsonToken::ArrayOpen(_) => "array",
+        JsonToken::StringValue { raw: _, value: _ } | JsonToken::PropertyName { raw: _, name: _ } => "string",
+        JsonToken::IntegerValue { raw: _, value: _ } | JsonToken::FloatValue { raw: _, value: _ } | JsonToken::BigIntegerValue(_) => "number",
+        JsonToken::BooleanValue { raw: _, value: _ } => "boolean",
+        JsonToken::NullValue(_) => "null",
+        _ => "undefined",
+    }
+}
+
+/// Gathers the tokens of each match into a record of its own. Matches can be nested, e.g. `$..*` matches an object and
+/// each of its members, so a token is added to every match that is open and a match waits to be written out until the
+/// ones that started before it have been. The whitespace around a match isn't part of it.
+///
+/// The query hands tokens back in the order they were given to it, so where each one starts is queued up alongside it.
+struct JsonRecords {
+    file: String,
+    starts: VecDeque<JsonPosition>,
+    records: VecDeque<JsonRecord>,
+    open: Vec<usize>,
+    written: usize,
+}
+
+struct JsonRecord {
+    location: String,
+    path: Option<String>,
+    tokens: Vec<JsonToken>,
+    is_complete: bool,
+}
+
+impl JsonRecords {
+    fn new(file: Option<&Path>) -> JsonRecords {
+        JsonRecords {
+            file: file.map_or_else(|| String::from("-"), |file| file.display().to_string()),
+            starts: VecDeque::new(),
+            records: VecDeque::new(),
+            open: Vec::new(),
+            written: 0,
+        }
+    }
+
+    fn push_token(&mut self, token: JsonToken, start: JsonPosition, boundary: JsonMatchBoundary, path: Option<String>) {
+        if matches!(boundary, JsonMatchBoundary::Start | JsonMatchBoundary::Whole) {
+            self.open.push(self.written + self.records.len());
+            self.records.push_back(JsonRecord {
+                location: self.location(start),
+                path,
+                tokens: Vec::new(),
+                is_complete: false,
+            });
+        }
+
+        for record in &self.open {
+            self.records[record - self.written].tokens.push(token.clone());
+        }
+
+        if matches!(boundary, JsonMatchBoundary::End | JsonMatchBoundary::Whole) {
+            if let Some(record) = self.open.pop() {
+                self.records[record - self.written].is_complete = true;
+            }
+        }
+    }
+
+    fn location(&self, start: JsonPosition) -> String {
+        format!("{}:{}:{}", self.file, start.line, start.column)
+    }
+
+    /// Hands back the next match to be written out if all of it has gone past.
+    fn pop_record(&mut self) -> Option<JsonRecord> {
+        match self.records.front() {
+            Some(record) if record.is_complete => {
+                self.written += 1;
+                self.records.pop_front()
             }
+            _ => None,
         }
     }
+}
+
+/// Reads a JSON path from the arguments, or a JSON pointer if `--pointer` is given or it starts with `/`. Syntax that isn't
+/// standard JSONPath is only accepted if `--strict-jsonpath` isn't given.
+fn json_path(args: &SSEditArgs, path: &str) -> io::Result<JsonPath> {
+    if args.pointer || path.starts_with('/') {
+        return JsonPath::parse_pointer(path).map_err(|err| invalid_input(err.to_string()));
+    }
+
+    let json_path = JsonPath::parse(path).map_err(|err| invalid_input(err.to_string()))?;
+
+    match json_path.non_standard() {
+        Some(non_standard) if args.strict_jsonpath => Err(invalid_input(format!("'{}' isn't standard JSONPath, it has {}", path, non_standard))),
+        _ => Ok(json_path),
+    }
+}
+
+/// Reads the editing operation from the arguments along with the path it applies to, if one was given.
+fn json_edit_operation(args: &SSEditArgs) -> io::Result<Option<(&str, JsonEdit)>> {
+    if let Some(set) = &args.set {
+        let (path, value) = split_assignment(set).ok_or_else(|| invalid_input(format!("expected PATH=VALUE but found '{}'", set)))?;
+
+        return Ok(Some((path, JsonEdit::set(value).map_err(invalid_input)?)));
+    }
 
-    json_lexer.close();
+    if let Some(path) = &args.delete {
+        return Ok(Some((path, JsonEdit::Delete)));
+    }
+
+    if let Some(insert) = &args.insert {
+        let (name, value) = insert[1].split_once('=').ok_or_else(|| invalid_input(format!("expected KEY=VALUE but found '{}'", insert[1])))?;
+
+        return Ok(Some((&insert[0], JsonEdit::insert(name, value).map_err(invalid_input)?)));
+    }
+
+    if let Some(append) = &args.append {
+        return Ok(Some((&append[0], JsonEdit::append(&append[1]).map_err(invalid_input)?)));
+    }
+
+    if let Some(prepend) = &args.prepend {
+        return Ok(Some((&prepend[0], JsonEdit::prepend(&prepend[1]).map_err(invalid_input)?)));
+    }
+
+    if let Some(rename) = &args.rename {
+        return Ok(Some((&rename[0], JsonEdit::Rename(rename[1].clone()))));
+    }
+
+    Ok(None)
+}
+
+fn json_edit(args: &SSEditArgs, path: &str, edit: JsonEdit, input: &mut dyn Read, output: &mut dyn Write) -> io::Result<()> {
+    let edit_path = json_path(args, path)?;
+    let mut json_editor = JsonStreamEditor::from(&edit_path, edit);
+
+    let mut json_lexer = JsonStreamLexer::new();
+
+    let mut reader = Utf8Reader::new(input);
+
+    while let Some(text) = reader.read_str()? {
+        for c in text.chars() {
+            json_lexer.push_char(c).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+            while let JsonStreamStatus::Token(token) = json_lexer.pop_token() {
+                json_editor.push_token(token).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+            }
+
+            while let JsonStreamStatus::Token(token) = json_editor.pop_token() {
+                write!(output, "{}", token.raw())?;
+            }
+        }
+    }
+
+    json_lexer.close().map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+    while let JsonStreamStatus::Token(token) = json_lexer.pop_token() {
+        json_editor.push_token(token).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    }
+
+    json_editor.close().map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+    while let JsonStreamStatus::Token(token) = json_editor.pop_token() {
+        write!(output, "{}", token.raw())?;
+    }
 
     Ok(())
 }
 
-fn yaml_parse(args: SSEditArgs) -> io::Result<()> {
-    let mut buffer = [0; 1];
+/// Splits a `PATH=VALUE` argument on the first `=` that isn't part of a bracketed or quoted section of the path.
+fn split_assignment(assignment: &str) -> Option<(&str, &str)> {
+    let mut depth = 0;
+    let mut quote = None;
+
+    for (i, c) in assignment.char_indices() {
+        match (quote, c) {
+            (Some(q), _) if q == c => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '[' | '(') => depth += 1,
+            (None, ']' | ')') => depth -= 1,
+            (None, '=') if depth == 0 => return Some((&assignment[..i], &assignment[i + 1..])),
+            _ => {}
+        }
+    }
+
+    None
+}
 
-    let query_path_str = if args.query.is_empty() {
+fn yaml_parse(args: &SSEditArgs, input: &mut dyn Read, output: &mut dyn Write) -> io::Result<()> {
+    if args.query.as_deref().unwrap_or_default().is_empty() {
         eprintln!("no select command provided");
         return Ok(());
-    } else {
-        args.query.as_str()
-    };
+    }
 
     let mut yaml_lexer = YamlStreamLexer::new();
 
-    loop {
-        match io::stdin().lock().read(&mut buffer) {
-            Ok(0) => break,
-            Ok(_) => {
-                let c = buffer[0] as char;
+    let mut reader = Utf8Reader::new(input);
 
-                if let Err(msg) = yaml_lexer.push_char(c) {
-                    panic!("{}", msg);
-                }
+    while let Some(text) = reader.read_str()? {
+        for c in text.chars() {
+            if let Err(msg) = yaml_lexer.push_char(c) {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, msg.to_string()));
+            }
 
-                loop {
-                    match yaml_lexer.pop_token() {
-                        YamlStreamStatus::None => break,
-                        YamlStreamStatus::Token(token) => {
-                            if args.raw_symbols {
-                                match token {
-                                    YamlToken::PropertyName { raw, name } => print!("{}", raw),
-                                    YamlToken::BooleanValue { raw, value } => todo!(),
-                                    YamlToken::StringValue { raw, value } => todo!(),
-                                    YamlToken::IntegerValue { raw, value } => todo!(),
-                                    YamlToken::FloatValue { raw, value } => todo!(),
-                                    YamlToken::NullValue(_) => todo!(),
-                                    YamlToken::ObjectOpen(_) => todo!(),
-                                    YamlToken::ObjectClose(_) => todo!(),
-                                    YamlToken::ArrayOpen(_) => todo!(),
-                                    YamlToken::ArrayClose(_) => todo!(),
-                                    YamlToken::Whitespace(_) => todo!(),
-                                    YamlToken::NewLine(raw) => print!("{}", raw),
-                                    YamlToken::ArrayItemDelimiter(_) => todo!(),
-                                    YamlToken::PropertyDelimiter(_) => todo!(),
-                                    YamlToken::KeyValueDelimiter(_) => todo!(),
-                                    YamlToken::Content(_) => todo!(),
-                                    YamlToken::Paragraph(_) => todo!(),
-                                    YamlToken::Line(_) => todo!(),
-                                    YamlToken::ParagraphBreak(_) => todo!(),
-                                    YamlToken::Alias(_) => todo!(),
-                                    YamlToken::Dereference(_) => todo!(),
-                                    YamlToken::Comment(_) => todo!(),
-                                    YamlToken::YamlStart(raw) => print!("{}", raw),
-                                }
+            loop {
+                match yaml_lexer.pop_token() {
+                    YamlStreamStatus::None => break,
+                    YamlStreamStatus::Token(token) => {
+                        if args.raw_symbols {
+                            match token {
+                                YamlToken::PropertyName { raw, name: _ } => write!(output, "{}", raw)?,
+                                YamlToken::BooleanValue { raw: _, value: _ } => todo!(),
+                                YamlToken::StringValue { raw: _, value: _ } => todo!(),
+                                YamlToken::IntegerValue { raw: _, value: _ } => todo!(),
+                                YamlToken::FloatValue { raw: _, value: _ } => todo!(),
+                                YamlToken::NullValue(_) => todo!(),
+                                YamlToken::ObjectOpen(_) => todo!(),
+                                YamlToken::ObjectClose(_) => todo!(),
+                                YamlToken::ArrayOpen(_) => todo!(),
+                                YamlToken::ArrayClose(_) => todo!(),
+                                YamlToken::Whitespace(_) => todo!(),
+                                YamlToken::NewLine(raw) => write!(output, "{}", raw)?,
+                                YamlToken::ArrayItemDelimiter(_) => todo!(),
+                                YamlToken::PropertyDelimiter(_) => todo!(),
+                                YamlToken::KeyValueDelimiter(_) => todo!(),
+                                YamlToken::Content(_) => todo!(),
+                                YamlToken::Paragraph(_) => todo!(),
+                                YamlToken::Line(_) => todo!(),
+                                YamlToken::ParagraphBreak(_) => todo!(),
+                                YamlToken::Alias(_) => todo!(),
+                                YamlToken::Dereference(_) => todo!(),
+                                YamlToken::Comment(_) => todo!(),
+                                YamlToken::YamlStart(raw) => write!(output, "{}", raw)?,
                             }
                         }
                     }
                 }
             }
-            Err(_) => todo!(),
         }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("ssedit-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn query(args: &[&str], json: &str) -> String {
+        let args = SSEditArgs::parse_from(["ssedit"].iter().chain(args));
+        let mut output = Vec::new();
+
+        parse(&args, None, &mut json.as_bytes(), &mut output).unwrap();
+
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_records() {
+        let json = "{ \"a\": [ \"x\", true, null, { \"b\": 1 } ] }";
+
+        assert_eq!(query(&["-q", "$.a[*]"], json), "x\ntrue\nnull\n{ \"b\": 1 }\n");
+        assert_eq!(query(&["-0", "-q", "$.a[*]"], json), "x\0true\0null\0{ \"b\": 1 }\0");
+        assert_eq!(query(&["--json-array", "-q", "$.a[*]"], json), "[\"x\",true,null,{ \"b\": 1 }]\n");
+        assert_eq!(query(&["--json-array", "-q", "$.c"], json), "[]\n");
+        assert_eq!(query(&["-s", "-q", "$.a[0]"], json), "\"x\"\n");
+    }
+
+    #[test]
+    fn test_scalar_records() {
+        let json = "{ \"s\": \"a\\\"b\", \"i\": 10, \"f\": 1.5e1, \"t\": true, \"n\": null, \"o\": { \"a\": [1] } }";
+
+        assert_eq!(query(&["-q", "$.*"], json), "a\"b\n10\n1.5e1\ntrue\nnull\n{ \"a\": [1] }\n");
+        assert_eq!(query(&["-r", "-q", "$.*"], json), "a\"b\n10\n1.5e1\ntrue\nnull\n{ \"a\": [1] }\n");
+        assert_eq!(query(&["--json-output", "-q", "$.*"], json), "\"a\\\"b\"\n10\n1.5e1\ntrue\nnull\n{ \"a\": [1] }\n");
+    }
+
+    #[test]
+    fn test_nested_records() {
+        let json = "{ \"a\": { \"a\": [ 1, { \"a\": 2 } ] } }";
+
+        assert_eq!(query(&["-q", "$..a"], json), "{ \"a\": [ 1, { \"a\": 2 } ] }\n[ 1, { \"a\": 2 } ]\n2\n");
+        assert_eq!(query(&["--json-array", "-q", "$.a.a..*"], json), "[1,{ \"a\": 2 },2]\n");
+    }
+
+    #[test]
+    fn test_records_with_path() {
+        let json = "{ \"a\": [ \"x\", { \"b\": 1 } ] }";
+
+        assert_eq!(query(&["--with-path", "-q", "$.a[*]"], json), "$['a'][0]\tx\n$['a'][1]\t{ \"b\": 1 }\n");
+        assert_eq!(query(&["--with-path", "--json-array", "-q", "$..b"], json), "[{\"path\":\"$['a'][1]['b']\",\"value\":1}]\n");
+    }
+
+    #[test]
+    fn test_records_with_location() {
+        let json = "{\n\t\"a\": [ \"x\",\r\n\t\t{ \"b\": 1 } ]\n}";
+
+        assert_eq!(query(&["--with-location", "-q", "$.a[*]"], json), "-:2:9\tx\n-:3:3\t{ \"b\": 1 }\n");
+        assert_eq!(query(&["--with-location", "--with-path", "-q", "$..b"], json), "-:3:10\t$['a'][1]['b']\t1\n");
+        assert_eq!(
+            query(&["--with-location", "--with-path", "--json-array", "-q", "$..b"], json),
+            "[{\"location\":\"-:3:10\",\"path\":\"$['a'][1]['b']\",\"value\":1}]\n"
+        );
+    }
+
+    #[test]
+    fn test_count_and_type() {
+        let json = "{ \"a\": [ \"x\", 1, 2.5, true, null, { \"b\": [] } ] }";
+
+        assert_eq!(query(&["--count", "-q", "$.a[*]"], json), "6\n");
+        assert_eq!(query(&["--count", "-q", "$..*"], json), "8\n");
+        assert_eq!(query(&["--count", "-q", "$.c"], json), "0\n");
+        assert_eq!(query(&["--exists", "-q", "$..b"], json), "");
+        assert_eq!(query(&["--type", "-q", "$..*"], json), "array\nstring\nnumber\nnumber\nboolean\nnull\nobject\narray\n");
+        assert_eq!(query(&["--type", "--with-path", "-q", "$.a[-1]"], json), "$['a'][5]\tobject\n");
+        assert_eq!(query(&["--type", "--json-array", "-q", "$.a[0:2]"], json), "[\"string\",\"number\"]\n");
+    }
+
+    #[test]
+    fn test_expand_in_place_suffix() {
+        assert_eq!(expand_in_place_suffix(OsString::from("-i.bak")), OsString::from("--in-place=.bak"));
+        assert_eq!(expand_in_place_suffix(OsString::from("-i")), OsString::from("-i"));
+        assert_eq!(expand_in_place_suffix(OsString::from("-i=.bak")), OsString::from("-i=.bak"));
+        assert_eq!(expand_in_place_suffix(OsString::from("--in-place")), OsString::from("--in-place"));
+    }
+
+    #[test]
+    fn test_edit_in_place() {
+        let dir = temp_dir("in-place");
+        let file = dir.join("sample.json");
+
+        fs::write(&file, "{\n\t\"id\": \"0001\"\n}\n").unwrap();
+        fs::set_permissions(&file, Permissions::from_mode(0o640)).unwrap();
+
+        let args = SSEditArgs::parse_from(["ssedit", "--in-place=.bak", "--set", "$.id=\"0002\"", file.to_str().unwrap()]);
+
+        edit_in_place(&args, &file, ".bak").unwrap();
+
+        assert_eq!(fs::read_to_string(&file).unwrap(), "{\n\t\"id\": \"0002\"\n}\n");
+        assert_eq!(fs::read_to_string(dir.join("sample.json.bak")).unwrap(), "{\n\t\"id\": \"0001\"\n}\n");
+        assert_eq!(fs::metadata(&file).unwrap().permissions().mode() & 0o777, 0o640);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_edit_in_place_invalid_json() {
+        let dir = temp_dir("in-place-invalid");
+        let file = dir.join("invalid.json");
+
+        fs::write(&file, "{\n\t\"id\": [\"0001\"\n").unwrap();
+
+        let args = SSEditArgs::parse_from(["ssedit", "-i", "--set", "$.id[0]=\"0002\"", file.to_str().unwrap()]);
+
+        assert!(edit_in_place(&args, &file, "").is_err());
+        assert_eq!(fs::read_to_string(&file).unwrap(), "{\n\t\"id\": [\"0001\"\n");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
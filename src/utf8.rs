@@ -0,0 +1,112 @@
+use std::io::{self, Read};
+use std::str;
+
+const BUFFER_SIZE: usize = 8192;
+
+/// Reads UTF-8 text in chunks, handing back as much of each chunk as can be decoded. A character that is split across
+/// two reads is held back until the rest of it has been read.
+pub struct Utf8Reader<R: Read> {
+    input: R,
+    buffer: Vec<u8>,
+    len: usize,
+    decoded: usize,
+}
+
+impl<R: Read> Utf8Reader<R> {
+    pub fn new(input: R) -> Utf8Reader<R> {
+        Utf8Reader {
+            input,
+            buffer: vec![0; BUFFER_SIZE],
+            len: 0,
+            decoded: 0,
+        }
+    }
+
+    /// Reads the next chunk of text, returning `None` once the end of the input has been reached.
+    pub fn read_str(&mut self) -> io::Result<Option<&str>> {
+        // The bytes of a character that was cut off by the end of the last read are moved to the front.
+        self.buffer.copy_within(self.decoded..self.len, 0);
+        self.len -= self.decoded;
+        self.decoded = 0;
+
+        loop {
+            let read = match self.input.read(&mut self.buffer[self.len..]) {
+                Ok(read) => read,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            };
+
+            if read == 0 {
+                return match self.len {
+                    0 => Ok(None),
+                    _ => Err(invalid_utf8()),
+                };
+            }
+
+            self.len += read;
+
+            let valid = match str::from_utf8(&self.buffer[..self.len]) {
+                Ok(_) => self.len,
+                Err(err) if err.error_len().is_none() => err.valid_up_to(),
+                Err(_) => return Err(invalid_utf8()),
+            };
+
+            if valid > 0 {
+                self.decoded = valid;
+                return str::from_utf8(&self.buffer[..valid]).map(Some).map_err(|_| invalid_utf8());
+            }
+        }
+    }
+}
+
+fn invalid_utf8() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "the input is not valid UTF-8")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Hands out the input a few bytes at a time so characters get split across reads.
+    struct TrickleReader<'a> {
+        input: &'a [u8],
+        step: usize,
+    }
+
+    impl<'a> Read for TrickleReader<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = self.step.min(buf.len()).min(self.input.len());
+            buf[..len].copy_from_slice(&self.input[..len]);
+            self.input = &self.input[len..];
+            Ok(len)
+        }
+    }
+
+    fn read_all(input: &[u8], step: usize) -> io::Result<String> {
+        let mut reader = Utf8Reader::new(TrickleReader { input, step });
+        let mut output = String::new();
+
+        while let Some(text) = reader.read_str()? {
+            output.push_str(text);
+        }
+
+        Ok(output)
+    }
+
+    #[test]
+    fn test_split_characters() {
+        let text = "{ \"名前\": \"café 🍩\", \"ключ\": \"值\" }";
+
+        for step in 1..8 {
+            assert_eq!(read_all(text.as_bytes(), step).unwrap(), text);
+        }
+
+        assert_eq!(read_all(text.as_bytes(), BUFFER_SIZE).unwrap(), text);
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        assert!(read_all(b"{ \"a\": \"\xff\" }", 3).is_err());
+        assert!(read_all("🍩".as_bytes().split_last().unwrap().1, 1).is_err());
+    }
+}
@@ -2,6 +2,7 @@ use std::collections::VecDeque;
 
 use strum_macros::Display;
 
+#[allow(dead_code)]
 #[derive(Display, PartialEq)]
 pub enum YamlToken {
     PropertyName { raw: String, name: String },
@@ -29,6 +30,7 @@ pub enum YamlToken {
     YamlStart(String),
 }
 
+#[allow(dead_code)]
 pub enum YamlPartialToken {
     Array,
     Object,
@@ -65,8 +67,7 @@ pub struct YamlStreamLexer {
 
 impl YamlStreamLexer {
     pub fn new() -> YamlStreamLexer {
-        let mut partial_tokens = Vec::new();
-        partial_tokens.push(YamlPartialToken::Root);
+        let partial_tokens = vec![YamlPartialToken::Root];
 
         YamlStreamLexer {
             tokens: VecDeque::new(),
@@ -92,10 +93,10 @@ impl YamlStreamLexer {
                         YamlPartialToken::PropertyName => todo!(),
                         YamlPartialToken::PropertyValue => todo!(),
                         YamlPartialToken::ArrayValue => todo!(),
-                        YamlPartialToken::BooleanValue { raw, value } => todo!(),
-                        YamlPartialToken::StringValue { raw, value } => todo!(),
-                        YamlPartialToken::NullValue { raw } => todo!(),
-                        YamlPartialToken::UndefinedValue { raw } => todo!(),
+                        YamlPartialToken::BooleanValue { raw: _, value: _ } => todo!(),
+                        YamlPartialToken::StringValue { raw: _, value: _ } => todo!(),
+                        YamlPartialToken::NullValue { raw: _ } => todo!(),
+                        YamlPartialToken::UndefinedValue { raw: _ } => todo!(),
                         YamlPartialToken::Root => self.partial_tokens.push(YamlPartialToken::YamlStart(String::from(c))),
                         YamlPartialToken::NumberValue(_) => todo!(),
                         YamlPartialToken::Whitespace(_) => todo!(),
@@ -123,10 +124,10 @@ impl YamlStreamLexer {
                         YamlPartialToken::PropertyName => todo!(),
                         YamlPartialToken::PropertyValue => todo!(),
                         YamlPartialToken::ArrayValue => todo!(),
-                        YamlPartialToken::BooleanValue { raw, value } => todo!(),
-                        YamlPartialToken::StringValue { raw, value } => todo!(),
-                        YamlPartialToken::NullValue { raw } => todo!(),
-                        YamlPartialToken::UndefinedValue { raw } => todo!(),
+                        YamlPartialToken::BooleanValue { raw: _, value: _ } => todo!(),
+                        YamlPartialToken::StringValue { raw: _, value: _ } => todo!(),
+                        YamlPartialToken::NullValue { raw: _ } => todo!(),
+                        YamlPartialToken::UndefinedValue { raw: _ } => todo!(),
                         YamlPartialToken::Root => {
                             self.tokens.push_back(YamlToken::NewLine(String::from(c)));
                             self.partial_tokens.push(YamlPartialToken::Root);
@@ -0,0 +1,137 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Runs ssedit with `args`, writing `stdin` to it, and returns whether it succeeded along with what it wrote to stdout and
+/// stderr.
+fn ssedit(args: &[&str], stdin: &str) -> (bool, String, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_ssedit"))
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    // ssedit can exit before reading its input, e.g. on an invalid argument, so a broken pipe here isn't a failure.
+    let _ = child.stdin.take().unwrap().write_all(stdin.as_bytes());
+
+    let output = child.wait_with_output().unwrap();
+
+    (output.status.success(), String::from_utf8(output.stdout).unwrap(), String::from_utf8(output.stderr).unwrap())
+}
+
+fn query(args: &[&str], stdin: &str) -> String {
+    let (success, stdout, stderr) = ssedit(args, stdin);
+
+    assert!(success, "{}", stderr);
+
+    stdout
+}
+
+const SCALARS: &str = "{ \"string\": \"caf\\u00e9\", \"integer\": -7, \"float\": 0.5, \"boolean-test\": false, \"null-test\": null }";
+
+#[test]
+fn test_decoded_scalars() {
+    assert_eq!(query(&["-q", "$.string"], SCALARS), "café\n");
+    assert_eq!(query(&["-q", "$.integer"], SCALARS), "-7\n");
+    assert_eq!(query(&["-q", "$.float"], SCALARS), "0.5\n");
+    assert_eq!(query(&["-q", "$.boolean-test"], SCALARS), "false\n");
+    assert_eq!(query(&["-q", "$.null-test"], SCALARS), "null\n");
+    assert_eq!(query(&["-q", "$['boolean-test']"], SCALARS), "false\n");
+}
+
+#[test]
+fn test_json_output() {
+    assert_eq!(query(&["--json-output", "-q", "$.string"], SCALARS), "\"caf\\u00e9\"\n");
+    assert_eq!(query(&["--json-output", "-q", "$.*"], SCALARS), "\"caf\\u00e9\"\n-7\n0.5\nfalse\nnull\n");
+    assert_eq!(query(&["--json-array", "-q", "$.*"], SCALARS), "[\"caf\\u00e9\",-7,0.5,false,null]\n");
+}
+
+#[test]
+fn test_containers() {
+    assert_eq!(query(&["-q", "$.style", "sample.json"], ""), "[ \"hole\", \"filled\" ]\n");
+    assert_eq!(query(&["-q", "$.batters.batter[0]", "sample.json"], ""), "{ \"id\": \"1001\", \"type\": \"Regular\" }\n");
+}
+
+#[test]
+fn test_raw_and_json_output() {
+    assert_eq!(query(&["-r", "-q", "$.*"], SCALARS), "café\n-7\n0.5\nfalse\nnull\n");
+    assert_eq!(query(&["--raw-output", "-q", "$.string"], SCALARS), "café\n");
+    assert_eq!(query(&["--json-output", "-q", "$.string"], SCALARS), "\"caf\\u00e9\"\n");
+
+    for args in [["-r", "--json-output"], ["-r", "-s"], ["-r", "--json-array"]] {
+        let (success, stdout, stderr) = ssedit(&[args[0], args[1], "-q", "$.string"], SCALARS);
+
+        assert!(!success, "{:?}", args);
+        assert_eq!(stdout, "");
+        assert!(stderr.contains("cannot be used with"), "{}", stderr);
+    }
+}
+
+#[test]
+fn test_exists() {
+    assert_eq!(ssedit(&["--exists", "-q", "$.null-test"], SCALARS), (true, String::new(), String::new()));
+    assert_eq!(ssedit(&["--exists", "-q", "$.missing"], SCALARS), (false, String::new(), String::new()));
+    assert_eq!(ssedit(&["--exists", "-q", "$.missing", "sample.json", "sample2.json"], ""), (false, String::new(), String::new()));
+    assert_eq!(ssedit(&["--exists", "-q", "$.ppu", "sample2.json", "sample.json"], ""), (true, String::new(), String::new()));
+}
+
+#[test]
+fn test_count_per_file() {
+    assert_eq!(query(&["--count", "-q", "$..id", "sample.json", "sample2.json"], ""), "12\n7\n");
+}
+
+#[test]
+fn test_errors() {
+    let (success, stdout, stderr) = ssedit(&["-q", "$.a"], "{ \"b\": 1 x, \"a\": 2 }");
+
+    assert!(!success);
+    assert_eq!(stdout, "");
+    assert!(stderr.starts_with("ssedit: expected ',' or '}' but found 'x'"), "{}", stderr);
+}
+
+#[test]
+fn test_delete_root() {
+    let (success, stdout, stderr) = ssedit(&["--delete", "$"], "{ \"a\": 1 }");
+
+    assert!(!success);
+    assert_eq!(stdout, "");
+    assert_eq!(stderr, "ssedit: the whole document can't be deleted\n");
+}
+
+#[test]
+fn test_rename_array_item() {
+    let (success, _, stderr) = ssedit(&["--rename", "$.a[0]", "b"], "{ \"a\": [1] }");
+
+    assert!(!success);
+    assert_eq!(stderr, "ssedit: only a member of an object can be renamed\n");
+}
+
+#[test]
+fn test_insert_into_non_object() {
+    let (success, _, stderr) = ssedit(&["--insert", "$.a", "b=1"], "{ \"a\": [] }");
+
+    assert!(!success);
+    assert_eq!(stderr, "ssedit: a member can only be inserted into an object\n");
+}
+
+#[test]
+fn test_append_to_non_array() {
+    let (success, _, stderr) = ssedit(&["--append", "$.a", "1"], "{ \"a\": {} }");
+
+    assert!(!success);
+    assert_eq!(stderr, "ssedit: an item can only be added to an array\n");
+}
+
+#[test]
+fn test_strict_jsonpath_integers() {
+    for path in ["$[01]", "$[-0]"] {
+        let (success, _, stderr) = ssedit(&["--strict-jsonpath", "-q", path], "[1, 2]");
+
+        assert!(!success, "{}", path);
+        assert!(stderr.contains("with a leading zero"), "{}", stderr);
+    }
+
+    assert_eq!(query(&["-q", "$[01]"], "[1, 2]"), "2\n");
+}
@@ -0,0 +1,19 @@
+#!/bin/sh
+# Vendors cts.json and the licence from the JSONPath Compliance Test Suite at the given commit or tag, and records which
+# one it was in cts-version.txt. Run `cargo test test_jsonpath_compliance` afterwards to see which cases need adding to
+# or taking off known-failures.txt.
+set -eu
+
+if [ $# -ne 1 ]; then
+    echo "usage: $0 <commit or tag>" >&2
+    exit 2
+fi
+
+directory=$(dirname "$0")
+repository="jsonpath-standard/jsonpath-compliance-test-suite"
+commit=$(git ls-remote "https://github.com/$repository.git" "$1" "$1^{}" | tail -n 1 | cut -f 1)
+commit=${commit:-$1}
+
+curl -fsSL "https://raw.githubusercontent.com/$repository/$commit/cts.json" -o "$directory/cts.json"
+curl -fsSL "https://raw.githubusercontent.com/$repository/$commit/LICENSE" -o "$directory/cts-LICENSE"
+printf 'https://github.com/%s/tree/%s (%s)\n' "$repository" "$commit" "$1" > "$directory/cts-version.txt"